@@ -183,6 +183,35 @@ impl ArrayBufferLike for JsBufferSource {
     }
 }
 
+impl JsBufferSource {
+    /// Copies the bytes viewed by the buffer source (respecting the offset and
+    /// length of typed arrays and data views)
+    pub fn to_vec(&self, context: &mut Context<'_>) -> JsResult<Vec<u8>> {
+        let range = match self {
+            Self::ArrayBuffer(_) => None,
+            Self::ArrayBufferView(JsArrayBufferView::TypedArray(typed_array)) => {
+                let offset = typed_array.byte_offset(context)?;
+                Some(offset..offset + typed_array.byte_length(context)?)
+            }
+            Self::ArrayBufferView(JsArrayBufferView::DataView(data_view)) => {
+                let offset = data_view.byte_offset(context)? as usize;
+                Some(offset..offset + data_view.byte_length(context)? as usize)
+            }
+        };
+        let buffer_data = self.to_array_buffer_data(context)?;
+        let bytes = buffer_data.as_slice().ok_or_else(|| {
+            JsNativeError::typ().with_message("The array buffer is detached")
+        })?;
+        let bytes = match range {
+            Some(range) => bytes.get(range).ok_or_else(|| {
+                JsNativeError::range().with_message("Buffer view out of bounds")
+            })?,
+            None => &bytes[..],
+        };
+        Ok(bytes.to_vec())
+    }
+}
+
 // https://webidl.spec.whatwg.org/#idl-types
 
 pub type Any = JsValue;
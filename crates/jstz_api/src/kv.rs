@@ -1,13 +1,23 @@
-use std::ops::Deref;
+use std::{
+    collections::BTreeSet,
+    ops::{Bound, Deref},
+};
 
+use crate::idl::JsBufferSource;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use boa_engine::{
-    js_string, object::ObjectInitializer, property::Attribute, Context, JsArgs, JsError,
-    JsNativeError, JsResult, JsString, JsValue, NativeFunction,
+    js_string,
+    object::{
+        builtins::{JsArray, JsArrayBuffer, JsUint8Array},
+        JsObject, ObjectInitializer,
+    },
+    property::Attribute,
+    Context, JsArgs, JsError, JsNativeError, JsResult, JsString, JsValue, NativeFunction,
 };
-use boa_gc::{Finalize, Trace};
+use boa_gc::{Finalize, GcRef, Trace};
 use jstz_core::{host::HostRuntime, kv::Transaction, runtime, Result};
 use jstz_crypto::public_key_hash::PublicKeyHash;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use tezos_smart_rollup::storage::path::{self, OwnedPath, RefPath};
 
 #[derive(Debug, Trace, Finalize)]
@@ -16,23 +26,248 @@ pub struct Kv {
 }
 
 const KV_PATH: RefPath = RefPath::assert_from(b"/jstz_kv");
+const KV_INDEX_PATH: RefPath = RefPath::assert_from(b"/jstz_kv_index");
+
+/// The maximum number of keys in a page of a `KvIndex`
+const KV_INDEX_PAGE_SIZE: usize = 64;
+
+/// The sorted keys of a `Kv`. The durable storage cannot enumerate subkeys
+/// from within the kernel, so keys are indexed as they are written.
+///
+/// Keys are split into pages of at most `KV_INDEX_PAGE_SIZE` keys, each
+/// stored under the index at `/<page id>`, so that adding or removing a key
+/// only rewrites a single page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KvIndex {
+    /// The lowest key and id of each page, in order. The lowest key of the
+    /// first page is ignored.
+    pages: Vec<(String, u32)>,
+    next_page_id: u32,
+    /// Whether keys were stored before the index was created. Such keys are
+    /// only indexed once they are set again, so listing fails until the
+    /// smart function marks them as listed.
+    has_unlisted_keys: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct KvIndexPage(BTreeSet<String>);
+
+impl KvIndex {
+    /// Returns the position of the page that holds (or would hold) `key`
+    fn page_position(&self, key: &str) -> Option<usize> {
+        if self.pages.is_empty() {
+            return None;
+        }
+        let position = self
+            .pages
+            .partition_point(|(lowest_key, _)| lowest_key.as_str() <= key);
+        Some(position.saturating_sub(1))
+    }
+
+    fn page_id(&self, key: &str) -> Option<(usize, u32)> {
+        let position = self.page_position(key)?;
+        Some((position, self.pages[position].1))
+    }
+
+    fn add_page(&mut self, position: usize, lowest_key: String) -> u32 {
+        let id = self.next_page_id;
+        self.next_page_id += 1;
+        self.pages.insert(position, (lowest_key, id));
+        id
+    }
+}
+
+/// Options for listing the keys of a `Kv`
+#[derive(Debug, Clone, Default)]
+pub struct KvListOptions {
+    /// The maximum number of keys to return
+    pub limit: Option<usize>,
+    /// Only keys strictly greater than the cursor are returned
+    pub cursor: Option<String>,
+}
+
+/// A value stored in `Kv`.
+///
+/// Values are stored as a byte string. JSON values are stored as their JSON
+/// text (the encoding used before strings and bytes were supported), whereas
+/// strings and bytes are prefixed by a tag that cannot start a JSON text.
+///
+/// In human-readable formats, JSON values are likewise represented by their
+/// JSON text, whereas strings and bytes are tagged, e.g. `{"string": "foo"}`
+/// and `{"bytes": "<base64>"}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvValue {
+    Json(serde_json::Value),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+const STRING_TAG: u8 = 0x00;
+const BYTES_TAG: u8 = 0x01;
+
+/// The human-readable representation of a `KvValue`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum HumanReadableKvValue {
+    Json(String),
+    Tagged(TaggedKvValue),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TaggedKvValue {
+    String(String),
+    /// Base64 encoded bytes
+    Bytes(String),
+}
+
+impl KvValue {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Json(value) => value.to_string().into_bytes(),
+            Self::String(string) => [&[STRING_TAG][..], string.as_bytes()].concat(),
+            Self::Bytes(bytes) => [&[BYTES_TAG][..], bytes.as_slice()].concat(),
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, String> {
+        match bytes.split_first() {
+            Some((&STRING_TAG, string)) => String::from_utf8(string.to_vec())
+                .map(Self::String)
+                .map_err(|err| err.to_string()),
+            Some((&BYTES_TAG, bytes)) => Ok(Self::Bytes(bytes.to_vec())),
+            _ => serde_json::from_slice(bytes)
+                .map(Self::Json)
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Returns the value as JSON, representing bytes as an array of numbers
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Json(value) => value.clone(),
+            Self::String(string) => serde_json::Value::String(string.clone()),
+            Self::Bytes(bytes) => bytes.iter().copied().collect(),
+        }
+    }
 
-// TODO: Figure out a more effective way of serializing values using json
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(try_from = "String", into = "String")]
-pub struct KvValue(pub serde_json::Value);
+    /// Converts a JavaScript value, storing strings and buffer sources as is
+    /// and any other value as JSON
+    pub fn from_js(value: &JsValue, context: &mut Context<'_>) -> JsResult<Self> {
+        if let Some(string) = value.as_string() {
+            return Ok(Self::String(string.to_std_string_escaped()));
+        }
+
+        if value.as_object().is_some_and(|obj| {
+            obj.is_array_buffer() || obj.is_typed_array() || obj.is_data_view()
+        }) {
+            let buffer_source: JsBufferSource = value.try_js_into(context)?;
+            return Ok(Self::Bytes(buffer_source.to_vec(context)?));
+        }
+
+        Ok(Self::Json(value.to_json(context)?))
+    }
 
-impl From<KvValue> for String {
-    fn from(val: KvValue) -> Self {
-        val.0.to_string()
+    /// Converts the value to JavaScript, returning bytes as a `Uint8Array`
+    pub fn to_js(&self, context: &mut Context<'_>) -> JsResult<JsValue> {
+        match self {
+            Self::Json(value) => JsValue::from_json(value, context),
+            Self::String(string) => Ok(JsString::from(string.as_str()).into()),
+            Self::Bytes(bytes) => {
+                let uint8_array = JsUint8Array::from_array_buffer(
+                    JsArrayBuffer::from_byte_block(bytes.clone(), context)?,
+                    context,
+                )?;
+                Ok(uint8_array.into())
+            }
+        }
     }
 }
 
-impl TryFrom<String> for KvValue {
-    type Error = serde_json::Error;
+impl Serialize for KvValue {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let value = match self {
+                Self::Json(value) => HumanReadableKvValue::Json(value.to_string()),
+                Self::String(string) => {
+                    HumanReadableKvValue::Tagged(TaggedKvValue::String(string.clone()))
+                }
+                Self::Bytes(bytes) => HumanReadableKvValue::Tagged(TaggedKvValue::Bytes(
+                    BASE64_STANDARD.encode(bytes),
+                )),
+            };
+            value.serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
 
-    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_str(&value)?))
+impl<'de> Deserialize<'de> for KvValue {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            match HumanReadableKvValue::deserialize(deserializer)? {
+                HumanReadableKvValue::Json(json) => serde_json::from_str(&json)
+                    .map(Self::Json)
+                    .map_err(de::Error::custom),
+                HumanReadableKvValue::Tagged(TaggedKvValue::String(string)) => {
+                    Ok(Self::String(string))
+                }
+                HumanReadableKvValue::Tagged(TaggedKvValue::Bytes(bytes)) => {
+                    BASE64_STANDARD
+                        .decode(bytes)
+                        .map(Self::Bytes)
+                        .map_err(de::Error::custom)
+                }
+            }
+        } else {
+            let bytes = ByteBuf::deserialize(deserializer)?;
+            Self::from_bytes(&bytes.0).map_err(de::Error::custom)
+        }
+    }
+}
+
+/// Deserializes a byte string, accepting values serialized as strings
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        struct ByteBufVisitor;
+
+        impl<'de> de::Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a byte string")
+            }
+
+            fn visit_bytes<E: de::Error>(
+                self,
+                v: &[u8],
+            ) -> std::result::Result<ByteBuf, E> {
+                Ok(ByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(
+                self,
+                v: Vec<u8>,
+            ) -> std::result::Result<ByteBuf, E> {
+                Ok(ByteBuf(v))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<ByteBuf, E> {
+                Ok(ByteBuf(v.as_bytes().to_vec()))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
     }
 }
 
@@ -47,8 +282,138 @@ impl Kv {
         Ok(path::concat(&KV_PATH, &key_path)?)
     }
 
-    pub fn set(&self, tx: &mut Transaction, key: &str, value: KvValue) -> Result<()> {
-        tx.insert(self.key_path(key)?, value)
+    fn index_path(&self) -> jstz_core::Result<OwnedPath> {
+        let index_path = OwnedPath::try_from(format!("/{}", self.prefix))?;
+
+        Ok(path::concat(&KV_INDEX_PATH, &index_path)?)
+    }
+
+    fn page_path(&self, id: u32) -> jstz_core::Result<OwnedPath> {
+        let page_path = OwnedPath::try_from(format!("/{}", id))?;
+
+        Ok(path::concat(&self.index_path()?, &page_path)?)
+    }
+
+    /// Returns `true` if keys were written to the durable storage, whether
+    /// indexed or not
+    fn has_stored_keys(&self, hrt: &impl HostRuntime) -> Result<bool> {
+        let kv_path =
+            path::concat(&KV_PATH, &OwnedPath::try_from(format!("/{}", self.prefix))?)?;
+
+        Ok(hrt.store_has(&kv_path)?.is_some())
+    }
+
+    fn index_key(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        key: &str,
+    ) -> Result<()> {
+        let index_path = self.index_path()?;
+        let page = match tx.get::<KvIndex>(hrt, index_path.clone())? {
+            Some(index) => index.page_id(key),
+            None => {
+                let index = KvIndex {
+                    has_unlisted_keys: self.has_stored_keys(hrt)?,
+                    ..KvIndex::default()
+                };
+                tx.insert(index_path.clone(), index)?;
+                None
+            }
+        };
+
+        let (position, id) = match page {
+            Some(page) => page,
+            None => {
+                let id = tx
+                    .entry::<KvIndex>(hrt, index_path)?
+                    .or_insert_default()
+                    .add_page(0, key.to_string());
+                let page = KvIndexPage(BTreeSet::from([key.to_string()]));
+                return tx.insert(self.page_path(id)?, page);
+            }
+        };
+
+        // Only rewrite the page when a new key is added
+        let page_path = self.page_path(id)?;
+        let is_indexed = tx
+            .get::<KvIndexPage>(hrt, page_path.clone())?
+            .is_some_and(|page| page.0.contains(key));
+        if is_indexed {
+            return Ok(());
+        }
+
+        let page = tx.entry::<KvIndexPage>(hrt, page_path)?.or_insert_default();
+        page.0.insert(key.to_string());
+        if page.0.len() <= KV_INDEX_PAGE_SIZE {
+            return Ok(());
+        }
+
+        // Split full pages in half
+        let middle_key = page.0.iter().nth(page.0.len() / 2).cloned();
+        let upper_half = match middle_key {
+            Some(middle_key) => page.0.split_off(&middle_key),
+            None => return Ok(()),
+        };
+        let lowest_key = upper_half.first().cloned().unwrap_or_default();
+        let id = tx
+            .entry::<KvIndex>(hrt, self.index_path()?)?
+            .or_insert_default()
+            .add_page(position + 1, lowest_key);
+        tx.insert(self.page_path(id)?, KvIndexPage(upper_half))
+    }
+
+    fn unindex_key(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        key: &str,
+    ) -> Result<()> {
+        let index_path = self.index_path()?;
+        let page = tx
+            .get::<KvIndex>(hrt, index_path.clone())?
+            .and_then(|index| index.page_id(key));
+        let (position, id) = match page {
+            Some(page) => page,
+            None => return Ok(()),
+        };
+
+        let page_path = self.page_path(id)?;
+        let is_indexed = tx
+            .get::<KvIndexPage>(hrt, page_path.clone())?
+            .is_some_and(|page| page.0.contains(key));
+        if !is_indexed {
+            return Ok(());
+        }
+
+        let is_empty = match tx.get_mut::<KvIndexPage>(hrt, page_path.clone())? {
+            Some(page) => {
+                page.0.remove(key);
+                page.0.is_empty()
+            }
+            None => false,
+        };
+        // The lowest key of a page remains a valid lower bound when keys are
+        // removed, so the index is only rewritten when a page is emptied
+        if is_empty {
+            tx.remove(page_path)?;
+            if let Some(index) = tx.get_mut::<KvIndex>(hrt, index_path)? {
+                index.pages.remove(position);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        key: &str,
+        value: KvValue,
+    ) -> Result<()> {
+        tx.insert(self.key_path(key)?, value)?;
+        self.index_key(hrt, tx, key)
     }
 
     pub fn get<'a>(
@@ -60,8 +425,106 @@ impl Kv {
         tx.get::<KvValue>(hrt, self.key_path(key)?)
     }
 
-    pub fn delete(&self, tx: &mut Transaction, key: &str) -> Result<()> {
-        tx.remove(self.key_path(key)?)
+    pub fn delete(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        key: &str,
+    ) -> Result<()> {
+        tx.remove(self.key_path(key)?)?;
+        self.unindex_key(hrt, tx, key)
+    }
+
+    /// Returns the keys starting with `prefix` in lexicographic order,
+    /// including the uncommitted writes of the transaction
+    pub fn keys(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        prefix: &str,
+        options: &KvListOptions,
+    ) -> Result<Vec<String>> {
+        let index = match tx.get::<KvIndex>(hrt, self.index_path()?)? {
+            Some(index) => index.clone(),
+            None => return Ok(Vec::new()),
+        };
+
+        let start = match &options.cursor {
+            Some(cursor) if cursor.as_str() >= prefix => Bound::Excluded(cursor.as_str()),
+            _ => Bound::Included(prefix),
+        };
+        let start_key = match start {
+            Bound::Included(key) | Bound::Excluded(key) => key,
+            Bound::Unbounded => prefix,
+        };
+        let position = match index.page_position(start_key) {
+            Some(position) => position,
+            None => return Ok(Vec::new()),
+        };
+        let limit = options.limit.unwrap_or(usize::MAX);
+
+        let mut keys = Vec::new();
+        for (_, id) in &index.pages[position..] {
+            let page = match tx.get::<KvIndexPage>(hrt, self.page_path(*id)?)? {
+                Some(page) => page,
+                None => continue,
+            };
+            for key in page.0.range::<str, _>((start, Bound::Unbounded)) {
+                if keys.len() == limit || !key.starts_with(prefix) {
+                    return Ok(keys);
+                }
+                keys.push(key.clone());
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Returns `true` if keys were stored before the index was created, in
+    /// which case they are not listed until they are set again
+    pub fn has_unlisted_keys(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+    ) -> Result<bool> {
+        match tx.get::<KvIndex>(hrt, self.index_path()?)? {
+            Some(index) => Ok(index.has_unlisted_keys),
+            None => self.has_stored_keys(hrt),
+        }
+    }
+
+    /// Records that the keys stored before the index was created have been set
+    /// again, so that all keys are listed
+    pub fn mark_listed(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+    ) -> Result<()> {
+        tx.entry::<KvIndex>(hrt, self.index_path()?)?
+            .or_insert_default()
+            .has_unlisted_keys = false;
+        Ok(())
+    }
+
+    /// Returns the key-value pairs whose keys start with `prefix`, in
+    /// lexicographic order of their keys
+    pub fn entries(
+        &self,
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        prefix: &str,
+        options: &KvListOptions,
+    ) -> Result<Vec<(String, KvValue)>> {
+        let keys = self.keys(hrt, tx, prefix, options)?;
+
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(value) = self.get(hrt, tx, &key)? {
+                entries.push((key, value.clone()));
+            }
+        }
+
+        Ok(entries)
     }
 
     pub fn has(
@@ -74,6 +537,19 @@ impl Kv {
     }
 }
 
+impl Kv {
+    fn try_from_js(value: &JsValue) -> JsResult<GcRef<'_, Self>> {
+        value
+            .as_object()
+            .and_then(|obj| obj.downcast_ref::<Self>())
+            .ok_or_else(|| {
+                JsNativeError::typ()
+                    .with_message("Failed to convert js value into rust type `Kv`")
+                    .into()
+            })
+    }
+}
+
 macro_rules! preamble {
     ($this:ident, $args:ident, $key:ident) => {
         let $this = $this
@@ -107,9 +583,9 @@ impl KvApi {
     fn set(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
         preamble!(this, args, key);
 
-        let value = KvValue(args.get_or_undefined(1).to_json(context)?);
+        let value = KvValue::from_js(args.get_or_undefined(1), context)?;
 
-        runtime::with_js_tx(|tx| this.set(tx, &key, value))?;
+        runtime::with_js_hrt_and_tx(|hrt, tx| this.set(hrt.deref(), tx, &key, value))?;
 
         Ok(JsValue::undefined())
     }
@@ -119,7 +595,7 @@ impl KvApi {
 
         runtime::with_js_hrt_and_tx(|hrt, tx| -> JsResult<JsValue> {
             match this.get(hrt.deref(), tx, &key)? {
-                Some(value) => JsValue::from_json(&value.0, context),
+                Some(value) => value.to_js(context),
                 None => Ok(JsValue::null()),
             }
         })
@@ -132,7 +608,7 @@ impl KvApi {
     ) -> JsResult<JsValue> {
         preamble!(this, args, key);
 
-        runtime::with_js_tx(|tx| this.delete(tx, &key))?;
+        runtime::with_js_hrt_and_tx(|hrt, tx| this.delete(hrt.deref(), tx, &key))?;
 
         Ok(JsValue::undefined())
     }
@@ -150,6 +626,194 @@ impl KvApi {
 
         Ok(result.into())
     }
+
+    fn list_options(value: &JsValue, context: &mut Context) -> JsResult<KvListOptions> {
+        let options = match value.as_object() {
+            Some(options) => options,
+            None if value.is_undefined() => return Ok(KvListOptions::default()),
+            None => {
+                return Err(JsNativeError::typ()
+                    .with_message("Expected options to be an object")
+                    .into())
+            }
+        };
+
+        let limit = options.get(js_string!("limit"), context)?;
+        let limit = if limit.is_undefined() {
+            None
+        } else {
+            Some(limit.to_length(context)? as usize)
+        };
+
+        let cursor = options.get(js_string!("cursor"), context)?;
+        let cursor = if cursor.is_null_or_undefined() {
+            None
+        } else {
+            Some(cursor.to_string(context)?.to_std_string_escaped())
+        };
+
+        Ok(KvListOptions { limit, cursor })
+    }
+
+    fn list_args(
+        args: &[JsValue],
+        context: &mut Context,
+    ) -> JsResult<(String, KvListOptions)> {
+        let prefix = match args.get_or_undefined(0) {
+            prefix if prefix.is_undefined() => String::new(),
+            prefix => prefix
+                .as_string()
+                .ok_or_else(|| {
+                    JsNativeError::typ().with_message(
+                        "Failed to convert js value into rust type `String`",
+                    )
+                })?
+                .to_std_string_escaped(),
+        };
+        let options = Self::list_options(args.get_or_undefined(1), context)?;
+
+        Ok((prefix, options))
+    }
+
+    /// Fails if keys stored before they could be listed would be omitted
+    fn ensure_listed(kv: &Kv) -> JsResult<()> {
+        let has_unlisted_keys =
+            runtime::with_js_hrt_and_tx(|hrt, tx| kv.has_unlisted_keys(hrt.deref(), tx))?;
+        if has_unlisted_keys {
+            return Err(JsNativeError::error()
+                .with_message(
+                    "Kv has keys stored before they could be listed. \
+                     Set them again and call Kv.markListed() to list keys.",
+                )
+                .into());
+        }
+        Ok(())
+    }
+
+    fn keys(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context,
+    ) -> JsResult<JsValue> {
+        let this = Kv::try_from_js(this)?;
+        let (prefix, options) = Self::list_args(args, context)?;
+        Self::ensure_listed(&this)?;
+
+        let keys = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            this.keys(hrt.deref(), tx, &prefix, &options)
+        })?;
+
+        let keys = keys.into_iter().map(|key| JsString::from(key).into());
+
+        Ok(JsArray::from_iter(keys, context).into())
+    }
+
+    fn entries(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context,
+    ) -> JsResult<JsValue> {
+        let this = Kv::try_from_js(this)?;
+        let (prefix, options) = Self::list_args(args, context)?;
+        Self::ensure_listed(&this)?;
+
+        let entries = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            this.entries(hrt.deref(), tx, &prefix, &options)
+        })?;
+
+        let mut result = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            let entry = [JsString::from(key).into(), value.to_js(context)?];
+            result.push(JsArray::from_iter(entry, context).into());
+        }
+
+        Ok(JsArray::from_iter(result, context).into())
+    }
+
+    fn has_unlisted_keys(
+        this: &JsValue,
+        _args: &[JsValue],
+        _context: &mut Context,
+    ) -> JsResult<JsValue> {
+        let this = Kv::try_from_js(this)?;
+
+        let result = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            this.has_unlisted_keys(hrt.deref(), tx)
+        })?;
+
+        Ok(result.into())
+    }
+
+    fn mark_listed(
+        this: &JsValue,
+        _args: &[JsValue],
+        _context: &mut Context,
+    ) -> JsResult<JsValue> {
+        let this = Kv::try_from_js(this)?;
+
+        runtime::with_js_hrt_and_tx(|hrt, tx| this.mark_listed(hrt.deref(), tx))?;
+
+        Ok(JsValue::undefined())
+    }
+
+    /// Runs the callback in a nested transaction (a savepoint), committing its
+    /// writes if it returns and rolling them back if it throws. The callback
+    /// must be synchronous: writes made after an `await` could not be rolled
+    /// back with the savepoint.
+    fn transaction(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context,
+    ) -> JsResult<JsValue> {
+        Kv::try_from_js(this)?;
+
+        let callback = args
+            .get_or_undefined(0)
+            .as_callable()
+            .ok_or_else(|| JsNativeError::typ().with_message("Expected a function"))?;
+        let async_function_prototype = context
+            .intrinsics()
+            .constructors()
+            .async_function()
+            .prototype();
+        if callback.prototype().is_some_and(|prototype| {
+            JsObject::equals(&prototype, &async_function_prototype)
+        }) {
+            return Err(Self::async_callback_error());
+        }
+
+        runtime::with_js_tx(|tx| tx.begin());
+
+        let value = match callback.call(&JsValue::undefined(), &[], context) {
+            Ok(value) if value.as_promise().is_some() => {
+                runtime::with_js_tx(|tx| tx.rollback())?;
+                return Err(Self::async_callback_error());
+            }
+            Ok(value) => value,
+            Err(err) => {
+                runtime::with_js_tx(|tx| tx.rollback())?;
+                return Err(err);
+            }
+        };
+
+        runtime::with_js_hrt_and_tx(|hrt, tx| -> JsResult<()> {
+            if let Err(err) = tx.commit(hrt) {
+                tx.rollback()?;
+                return Err(err.into());
+            }
+            Ok(())
+        })?;
+
+        Ok(value)
+    }
+
+    fn async_callback_error() -> JsError {
+        JsNativeError::typ()
+            .with_message(
+                "Kv.transaction() callbacks must not be async or return a promise",
+            )
+            .into()
+    }
 }
 
 impl jstz_core::Api for KvApi {
@@ -164,6 +828,31 @@ impl jstz_core::Api for KvApi {
                     1,
                 )
                 .function(NativeFunction::from_fn_ptr(Self::has), js_string!("has"), 1)
+                .function(
+                    NativeFunction::from_fn_ptr(Self::keys),
+                    js_string!("keys"),
+                    2,
+                )
+                .function(
+                    NativeFunction::from_fn_ptr(Self::entries),
+                    js_string!("entries"),
+                    2,
+                )
+                .function(
+                    NativeFunction::from_fn_ptr(Self::has_unlisted_keys),
+                    js_string!("hasUnlistedKeys"),
+                    0,
+                )
+                .function(
+                    NativeFunction::from_fn_ptr(Self::mark_listed),
+                    js_string!("markListed"),
+                    0,
+                )
+                .function(
+                    NativeFunction::from_fn_ptr(Self::transaction),
+                    js_string!("transaction"),
+                    1,
+                )
                 .build();
 
         context
@@ -171,3 +860,22 @@ impl jstz_core::Api for KvApi {
             .expect("The storage object shouldn't exist yet");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::KvValue;
+
+    #[test]
+    fn human_readable_values_are_tagged() {
+        let values = [
+            (KvValue::Json(serde_json::json!("foo")), r#""\"foo\"""#),
+            (KvValue::String("foo".to_string()), r#"{"string":"foo"}"#),
+            (KvValue::Bytes(b"foo".to_vec()), r#"{"bytes":"Zm9v"}"#),
+        ];
+
+        for (value, json) in values {
+            assert_eq!(serde_json::to_string(&value).unwrap(), json);
+            assert_eq!(serde_json::from_str::<KvValue>(json).unwrap(), value);
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use bip39::{Language, Mnemonic, MnemonicType};
 use clap::Subcommand;
 use dialoguer::{Confirm, Input};
-use jstz_crypto::keypair_from_passphrase;
-use jstz_proto::context::account::Address;
+use jstz_crypto::{keypair_from_passphrase_and_curve, Curve};
+use jstz_proto::context::account::{Address, FunctionMetadata};
 use log::{debug, info, warn};
 use std::collections::hash_map::Entry;
 
@@ -18,8 +18,8 @@ fn generate_passphrase() -> String {
 }
 
 impl User {
-    pub fn from_passphrase(passphrase: String) -> Result<Self> {
-        let (sk, pk) = keypair_from_passphrase(passphrase.as_str())?;
+    pub fn from_passphrase(passphrase: String, curve: Curve) -> Result<Self> {
+        let (sk, pk) = keypair_from_passphrase_and_curve(passphrase.as_str(), curve)?;
 
         let address = Address::try_from(&pk)?;
 
@@ -49,7 +49,7 @@ fn add_smart_function(alias: String, address: Address) -> Result<()> {
     Ok(())
 }
 
-fn create_account(alias: String, passphrase: Option<String>) -> Result<()> {
+fn create_account(alias: String, passphrase: Option<String>, curve: Curve) -> Result<()> {
     let mut cfg = Config::load()?;
 
     if cfg.accounts.contains(&alias) {
@@ -68,7 +68,7 @@ fn create_account(alias: String, passphrase: Option<String>) -> Result<()> {
         }
     };
 
-    let user = User::from_passphrase(passphrase)?;
+    let user = User::from_passphrase(passphrase, curve)?;
 
     debug!("User created: {:?}", user);
     info!("User created with address: {}", user.address);
@@ -142,7 +142,7 @@ pub fn login(alias: String) -> Result<()> {
                 passphrase
             };
 
-            let user = User::from_passphrase(passphrase)?;
+            let user = User::from_passphrase(passphrase, Curve::default())?;
 
             entry.insert(user.into())
         }
@@ -253,12 +253,36 @@ async fn get_code(
 
     let address = AddressOrAlias::resolve_or_use_current_user(account, &cfg)?;
     debug!("resolved `account` -> {:?}", address);
-    let code = cfg
-        .jstz_client(&network)?
+    let jstz_client = cfg.jstz_client(&network)?;
+    let code = jstz_client
         .get_code(&address)
         .await?
         .ok_or(user_error!("No code found for account {}", address))?;
 
+    if let Some(metadata) = jstz_client.get_metadata(&address).await? {
+        let FunctionMetadata {
+            name,
+            version,
+            description,
+            source_hash,
+            bundler,
+        } = metadata;
+        let fields = [
+            ("Name", name),
+            ("Version", version),
+            ("Description", description),
+            ("Source hash", source_hash),
+            ("Bundler", bundler),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value {
+                info!("// {label}: {value}");
+            }
+        }
+        if let Some(code_hash) = jstz_client.get_code_hash(&address).await? {
+            info!("// Code hash: {code_hash}");
+        }
+    }
     info!("{}", code);
 
     Ok(())
@@ -291,6 +315,9 @@ pub enum Command {
         /// User passphrase. If undefined, a random passphrase will be generated.
         #[arg(short, long)]
         passphrase: Option<String>,
+        /// Elliptic curve of the account's key: ed25519 (tz1), secp256k1 (tz2) or p256 (tz3).
+        #[arg(short, long, default_value = "ed25519")]
+        curve: Curve,
     },
     /// ❌ Deletes an account (user or smart function).
     Delete {
@@ -339,7 +366,11 @@ pub enum Command {
 pub async fn exec(command: Command) -> Result<()> {
     match command {
         Command::Alias { alias, address } => add_smart_function(alias, address),
-        Command::Create { alias, passphrase } => create_account(alias, passphrase),
+        Command::Create {
+            alias,
+            passphrase,
+            curve,
+        } => create_account(alias, passphrase, curve),
         Command::Delete { alias } => delete_account(alias),
         Command::List { long } => list_accounts(long),
         Command::Code { account, network } => get_code(account, network).await,
@@ -1,6 +1,9 @@
 use clap::Subcommand;
 
 mod deposit;
+mod withdraw;
+
+use jstz_proto::context::account::Address;
 
 use crate::{config::NetworkName, error::Result, utils::AddressOrAlias};
 
@@ -22,9 +25,22 @@ pub enum Command {
         #[arg(short, long, default_value = None)]
         network: Option<NetworkName>,
     },
+    /// 💸 Withdraws tez from the current jstz account to a Tezos L1 address.
+    Withdraw {
+        /// Tezos L1 address to withdraw to.
+        #[arg(short, long)]
+        to: Address,
+        /// The amount in mutez to withdraw.
+        #[arg(short, long)]
+        amount: u64,
+        /// Specifies the network from the config file, defaulting to the configured default network.
+        /// Use `dev` for the local sandbox.
+        #[arg(short, long, default_value = None)]
+        network: Option<NetworkName>,
+    },
 }
 
-pub fn exec(command: Command) -> Result<()> {
+pub async fn exec(command: Command) -> Result<()> {
     match command {
         Command::Deposit {
             from,
@@ -32,5 +48,10 @@ pub fn exec(command: Command) -> Result<()> {
             amount,
             network,
         } => deposit::exec(from, to, amount, network),
+        Command::Withdraw {
+            to,
+            amount,
+            network,
+        } => withdraw::exec(to, amount, network).await,
     }
 }
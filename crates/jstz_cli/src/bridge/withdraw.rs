@@ -0,0 +1,87 @@
+use jstz_proto::{
+    context::account::Address,
+    operation::{Content, Operation, SignedOperation, Withdraw},
+    receipt::Content as ReceiptContent,
+};
+use log::{debug, info};
+
+use crate::{
+    account,
+    config::{Config, NetworkName},
+    error::{anyhow, bail, bail_user_error, Result},
+    term::styles,
+};
+
+pub async fn exec(to: Address, amount: u64, network: Option<NetworkName>) -> Result<()> {
+    let mut cfg = Config::load()?;
+
+    // Check network
+    if cfg.network_name(&network)? == NetworkName::Dev && cfg.sandbox.is_none() {
+        bail_user_error!(
+            "No sandbox is currently running. Please run {}.",
+            styles::command("jstz sandbox start")
+        );
+    }
+
+    // Get the current user and check if we are logged in
+    account::login_quick(&mut cfg)?;
+    cfg.reload()?;
+    let (user_name, user) = cfg.accounts.current_user().ok_or(anyhow!(
+        "Failed to setup the account. Please run `{}`.",
+        styles::command("jstz login")
+    ))?;
+
+    // Construct operation
+    let jstz_client = cfg.jstz_client(&network)?;
+
+    let nonce = jstz_client.get_nonce(&user.address).await?;
+    let rollup_address = jstz_client.get_rollup_address().await?;
+
+    debug!("Nonce: {:?}", nonce);
+
+    let op = Operation {
+        rollup_address,
+        expiry_level: None,
+        source: user.address.clone(),
+        nonce,
+        content: Content::Withdraw(Withdraw {
+            amount,
+            receiver: to.clone(),
+        }),
+    };
+
+    debug!("Operation: {:?}", op);
+
+    let hash = op.hash();
+
+    debug!("Operation hash: {}", hash.to_string());
+
+    let signed_op =
+        SignedOperation::new(user.public_key.clone(), user.secret_key.sign(&hash)?, op);
+
+    debug!("Signed operation: {:?}", signed_op);
+
+    // Send operation to jstz-node
+    jstz_client.post_operation(&signed_op).await?;
+    let receipt = jstz_client.wait_for_operation_receipt(&hash).await?;
+
+    debug!("Receipt: {:?}", receipt);
+
+    let outbox_message_id = match receipt.inner {
+        Ok(ReceiptContent::Withdraw(withdraw)) => withdraw.outbox_message_id,
+        Ok(_) => {
+            bail!("Expected a `Withdraw` receipt, but got something else.")
+        }
+        Err(err) => {
+            bail_user_error!("Failed to withdraw with error {err}.")
+        }
+    };
+
+    info!("Withdrew {} mutez from {} to {}", amount, user_name, to);
+    info!(
+        "The tez can be claimed on L1 by executing the outbox message at level {} with index {} once it is cemented.",
+        outbox_message_id.level, outbox_message_id.index
+    );
+
+    Ok(())
+}
@@ -1,7 +1,14 @@
+use std::{fs, path::Path};
+
 use boa_engine::JsError;
+use jstz_crypto::hash::Blake2b;
 use jstz_proto::{
-    context::account::ParsedCode,
-    operation::{Content, DeployFunction, Operation, SignedOperation},
+    context::account::{FunctionMetadata, ParsedCode},
+    executor::smart_function::deploy::DEFAULT_INIT_GAS_LIMIT,
+    operation::{
+        Content, DeployFunction, Operation, RevealDeployFunction, SignedOperation,
+    },
+    preimage::{self, PREIMAGE_HASH_SIZE},
     receipt::Content as ReceiptContent,
 };
 use log::{debug, info};
@@ -11,16 +18,67 @@ use crate::{
     config::{Config, NetworkName, SmartFunction},
     error::{anyhow, bail, bail_user_error, user_error, Result},
     term::styles,
-    utils::read_file_or_input_or_piped,
+    utils::{read_file_or_input, read_file_or_input_or_piped, AddressOrAlias},
 };
 
+/// Splits the code into reveal data pages, written to `preimages_dir`.
+/// Returns the root hash and the number of pages.
+fn write_preimages(code: &str, preimages_dir: &Path) -> Result<(Vec<u8>, usize)> {
+    let mut pages = Vec::new();
+    let root_hash = preimage::prepare(code.as_bytes(), |hash, page| {
+        let hash: &[u8; PREIMAGE_HASH_SIZE] = hash.as_ref();
+        pages.push((hex::encode(hash), page))
+    })
+    .map_err(|_| anyhow!("Failed to split the function code into preimages"))?;
+
+    fs::create_dir_all(preimages_dir)?;
+    let count = pages.len();
+    for (name, page) in pages {
+        fs::write(preimages_dir.join(name), page)?;
+    }
+
+    let root_hash: &[u8; PREIMAGE_HASH_SIZE] = root_hash.as_ref();
+    Ok((root_hash.to_vec(), count))
+}
+
+/// Reads the function's metadata from a JSON file or string, recording the
+/// hash of the original source file (if given).
+fn read_metadata(
+    metadata: Option<String>,
+    source: Option<String>,
+) -> Result<Option<FunctionMetadata>> {
+    if metadata.is_none() && source.is_none() {
+        return Ok(None);
+    }
+
+    let mut metadata: FunctionMetadata = match metadata {
+        Some(metadata) => serde_json::from_str(&read_file_or_input(metadata))
+            .map_err(|err| user_error!("Invalid function metadata: {err}"))?,
+        None => FunctionMetadata::default(),
+    };
+
+    if let Some(source) = source {
+        let source = fs::read(&source)
+            .map_err(|err| user_error!("Failed to read source file '{source}': {err}"))?;
+        metadata.source_hash = Some(Blake2b::from(&source).to_string());
+    }
+
+    Ok(Some(metadata))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn exec(
     code_op: Option<String>,
     balance: u64,
     name: Option<String>,
+    salt: Option<String>,
+    metadata: Option<String>,
+    source: Option<String>,
+    gas_limit: Option<u32>,
+    owner: Option<AddressOrAlias>,
     network: Option<NetworkName>,
 ) -> Result<()> {
-    // maximum size of code until the DAL is implemented
+    // maximum size of code that fits in a single inbox message
     const MAX_CODE_LENGTH: usize = 3915;
 
     let mut cfg = Config::load()?;
@@ -54,28 +112,66 @@ pub async fn exec(
     let jstz_client = cfg.jstz_client(&network)?;
 
     let nonce = jstz_client.get_nonce(&user.address).await?;
+    let rollup_address = jstz_client.get_rollup_address().await?;
 
     debug!("Nonce: {:?}", nonce);
 
     let code = read_file_or_input_or_piped(code_op)?
         .ok_or(user_error!("No function code supplied. Please provide a filename or pipe the file contents into stdin."))?;
 
-    if code.bytes().len() > MAX_CODE_LENGTH {
-        bail_user_error!("The data availability layer is not yet available. Smart functions are currently restricted to {MAX_CODE_LENGTH} bytes");
-    }
-
     debug!("Code: {}", code);
 
     let code: ParsedCode = code
         .try_into()
         .map_err(|err: JsError| user_error!("{err}"))?;
+
+    let metadata = read_metadata(metadata, source)?;
+    debug!("Metadata: {:?}", metadata);
+
+    let owner = owner.map(|owner| owner.resolve(&cfg)).transpose()?;
+
+    // Large smart functions are revealed from preimages placed in the
+    // rollup node's reveal data directory
+    let content = if code.to_string().len() > MAX_CODE_LENGTH {
+        let sandbox = match (cfg.network_name(&network)?, &cfg.sandbox) {
+            (NetworkName::Dev, Some(sandbox)) => sandbox,
+            _ => bail_user_error!("Smart functions larger than {MAX_CODE_LENGTH} bytes can currently only be deployed to the sandbox."),
+        };
+        let preimages_dir = sandbox.octez_rollup_node_dir.join("wasm_2_0_0");
+        let (root_hash, pages) = write_preimages(&code.to_string(), &preimages_dir)?;
+        debug!("Preimages written to {:?}", preimages_dir);
+
+        // The gas limit also pays for revealing the pages
+        let reveal_gas = pages * preimage::REVEAL_GAS_PER_PAGE;
+
+        Content::RevealDeployFunction(RevealDeployFunction {
+            root_hash,
+            account_credit: balance,
+            salt: salt.map(String::into_bytes),
+            metadata,
+            gas_limit: Some(
+                gas_limit.map_or(DEFAULT_INIT_GAS_LIMIT, |gas_limit| gas_limit as usize)
+                    + reveal_gas,
+            ),
+            owner,
+        })
+    } else {
+        Content::DeployFunction(DeployFunction {
+            function_code: code,
+            account_credit: balance,
+            salt: salt.map(String::into_bytes),
+            metadata,
+            gas_limit: gas_limit.map(|gas_limit| gas_limit as usize),
+            owner,
+        })
+    };
+
     let op = Operation {
+        rollup_address,
+        expiry_level: None,
         source: user.address.clone(),
         nonce,
-        content: Content::DeployFunction(DeployFunction {
-            function_code: code,
-            account_credit: balance,
-        }),
+        content,
     };
 
     debug!("Operation: {:?}", op);
@@ -101,7 +197,7 @@ pub async fn exec(
             bail!("Expected a `DeployFunction` receipt, but got something else.")
         }
         Err(err) => {
-            bail_user_error!("Failed to deploy smart function with error {err:?}.")
+            bail_user_error!("Failed to deploy smart function with error {err}.")
         }
     };
 
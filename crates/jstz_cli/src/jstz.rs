@@ -3,13 +3,14 @@ use std::time::Duration;
 use anyhow::{bail, Result};
 use jstz_api::KvValue;
 use jstz_proto::{
-    context::account::{Address, Nonce},
+    context::account::{Address, FunctionMetadata, Nonce},
     operation::{OperationHash, SignedOperation},
     receipt::Receipt,
 };
 use log::debug;
 use reqwest::StatusCode;
 use reqwest_eventsource::EventSource;
+use tezos_crypto_rs::hash::SmartRollupHash;
 use tokio::time::sleep;
 
 use crate::error::bail_user_error;
@@ -53,6 +54,18 @@ impl JstzClient {
         }
     }
 
+    pub async fn get_rollup_address(&self) -> Result<SmartRollupHash> {
+        let response = self
+            .get(&format!("{}/operations/rollup_address", self.endpoint))
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<SmartRollupHash>().await?),
+            // For any other status, return a generic error
+            _ => bail!("Failed to get the rollup address"),
+        }
+    }
+
     pub async fn get_nonce(&self, address: &Address) -> Result<Nonce> {
         let response = self
             .get(&format!("{}/accounts/{}/nonce", self.endpoint, address))
@@ -89,6 +102,45 @@ impl JstzClient {
         }
     }
 
+    pub async fn get_code_hash(&self, address: &Address) -> Result<Option<String>> {
+        let response = self
+            .get(&format!("{}/accounts/{}/code_hash", self.endpoint, address))
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let code_hash = response.json::<Option<String>>().await?;
+                Ok(code_hash)
+            }
+            StatusCode::NOT_FOUND => {
+                bail!("Account '{}' not found", address.to_base58())
+            }
+            // For any other status, return a generic error
+            _ => bail!("Failed to get the code hash"),
+        }
+    }
+
+    pub async fn get_metadata(
+        &self,
+        address: &Address,
+    ) -> Result<Option<FunctionMetadata>> {
+        let response = self
+            .get(&format!("{}/accounts/{}/metadata", self.endpoint, address))
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let metadata = response.json::<Option<FunctionMetadata>>().await?;
+                Ok(metadata)
+            }
+            StatusCode::NOT_FOUND => {
+                bail!("Account '{}' not found", address.to_base58())
+            }
+            // For any other status, return a generic error
+            _ => bail!("Failed to get the metadata"),
+        }
+    }
+
     pub async fn get_balance(&self, address: &Address) -> Result<u64> {
         let response = self
             .get(&format!("{}/accounts/{}/balance", self.endpoint, address))
@@ -15,6 +15,7 @@ mod repl;
 mod run;
 mod sandbox;
 mod term;
+mod transfer;
 mod utils;
 
 use config::{Config, NetworkName};
@@ -37,6 +38,21 @@ enum Command {
         /// Name (or alias) of the function.
         #[arg(long, default_value = None)]
         name: Option<String>,
+        /// Salt from which (with the code) the function's address is derived, instead of the account's nonce.
+        #[arg(long, default_value = None)]
+        salt: Option<String>,
+        /// Metadata of the function (name, version, description, source_hash, bundler), as JSON.
+        #[arg(long, value_name = "JSON|PATH", default_value = None)]
+        metadata: Option<String>,
+        /// Original (unbundled) source of the function, whose hash is recorded in its metadata.
+        #[arg(long, value_name = "PATH", default_value = None, value_hint = clap::ValueHint::FilePath)]
+        source: Option<String>,
+        /// Maximum amount of gas for evaluating the function and running its `init` export (if any) at deployment [default: 550000]
+        #[arg(short, long, default_value = None)]
+        gas_limit: Option<u32>,
+        /// jstz address or alias allowed to upgrade the function. Without an owner, the function can never be upgraded.
+        #[arg(long, value_name = "ADDRESS|ALIAS", default_value = None)]
+        owner: Option<AddressOrAlias>,
         /// Specifies the network from the config file, defaulting to the configured default network.
         /// Use `dev` for the local sandbox.
         #[arg(short, long, default_value = None)]
@@ -64,6 +80,19 @@ enum Command {
         #[arg(short, long)]
         trace: bool,
     },
+    /// 💸 Transfer tez from the current account to another jstz account
+    Transfer {
+        /// jstz address or alias to transfer to.
+        #[arg(short, long)]
+        to: AddressOrAlias,
+        /// The amount in mutez to transfer.
+        #[arg(short, long)]
+        amount: u64,
+        /// Specifies the network from the config file, defaulting to the configured default network.
+        /// Use `dev` for the local sandbox.
+        #[arg(short, long, default_value = None)]
+        network: Option<NetworkName>,
+    },
     /// 🌉 Move CTEZ between L1 and jstz with the jstz bridge {n}
     #[command(subcommand)]
     Bridge(bridge::Command),
@@ -115,14 +144,24 @@ async fn exec(command: Command) -> Result<()> {
         Command::Docs => docs::exec(),
         Command::Completions { shell } => completions::exec(shell),
         Command::Sandbox(sandbox_command) => sandbox::exec(sandbox_command).await,
-        Command::Bridge(bridge_command) => bridge::exec(bridge_command),
+        Command::Bridge(bridge_command) => bridge::exec(bridge_command).await,
         Command::Account(account_command) => account::exec(account_command).await,
         Command::Deploy {
             code,
             balance,
             name,
+            salt,
+            metadata,
+            source,
+            gas_limit,
+            owner,
             network,
-        } => deploy::exec(code, balance, name, network).await,
+        } => {
+            deploy::exec(
+                code, balance, name, salt, metadata, source, gas_limit, owner, network,
+            )
+            .await
+        }
         Command::Run {
             url,
             http_method,
@@ -131,6 +170,11 @@ async fn exec(command: Command) -> Result<()> {
             network,
             trace,
         } => run::exec(url, http_method, gas_limit, json_data, network, trace).await,
+        Command::Transfer {
+            to,
+            amount,
+            network,
+        } => transfer::exec(to, amount, network).await,
         Command::Repl { account } => repl::exec(account),
         Command::Logs(logs) => logs::exec(logs).await,
         Command::Login { alias } => account::login(alias),
@@ -22,7 +22,7 @@ impl KvApi {
 
         runtime::with_js_hrt_and_tx(|hrt, tx| -> JsResult<JsValue> {
             match kv.get(hrt.deref(), tx, &key)? {
-                Some(value) => JsValue::from_json(&value.0, context),
+                Some(value) => value.to_js(context),
                 None => Ok(JsValue::null()),
             }
         })
@@ -36,11 +36,11 @@ impl KvApi {
         let account: String = args.get_or_undefined(0).try_js_into(context)?;
         let key: String = args.get_or_undefined(1).try_js_into(context)?;
 
-        let value = KvValue(args.get_or_undefined(2).to_json(context)?);
+        let value = KvValue::from_js(args.get_or_undefined(2), context)?;
 
         let kv = Kv::new(account);
 
-        runtime::with_js_tx(|tx| kv.set(tx, &key, value))?;
+        runtime::with_js_hrt_and_tx(|hrt, tx| kv.set(hrt.deref(), tx, &key, value))?;
 
         Ok(JsValue::undefined())
     }
@@ -55,7 +55,7 @@ impl KvApi {
 
         let kv = Kv::new(account);
 
-        runtime::with_js_tx(|tx| kv.delete(tx, &key))?;
+        runtime::with_js_hrt_and_tx(|hrt, tx| kv.delete(hrt.deref(), tx, &key))?;
 
         Ok(JsValue::undefined())
     }
@@ -5,8 +5,11 @@ use anyhow::bail;
 use http::{HeaderMap, Method, Uri};
 use jstz_proto::context::account::Address;
 use jstz_proto::{
+    js_logger::LogRecord,
     operation::{Content as OperationContent, Operation, RunFunction, SignedOperation},
-    receipt::Content as ReceiptContent,
+    receipt::{
+        Content as ReceiptContent, ReceiptError, RunFunction as ReceiptRunFunction,
+    },
 };
 use log::{debug, info};
 use spinners::{Spinner, Spinners};
@@ -71,6 +74,7 @@ pub async fn exec(
 
     // 3. Construct the signed operation
     let nonce = jstz_client.get_nonce(&user.address).await?;
+    let rollup_address = jstz_client.get_rollup_address().await?;
 
     // SAFETY: `url` is a valid URI since URLs are a subset of  URIs and `url_object` is a valid URL.
     let url: Uri = url_object
@@ -88,6 +92,8 @@ pub async fn exec(
     debug!("Body: {:?}", body);
 
     let op = Operation {
+        rollup_address,
+        expiry_level: None,
         source: user.address.clone(),
         nonce,
         content: OperationContent::RunFunction(RunFunction {
@@ -133,17 +139,37 @@ pub async fn exec(
     let receipt = jstz_client.wait_for_operation_receipt(&hash).await?;
 
     debug!("Receipt: {:?}", receipt);
-    let (status_code, headers, body) = match receipt.inner {
-        Ok(ReceiptContent::RunFunction(run_function)) => (
-            run_function.status_code,
-            run_function.headers,
-            run_function.body,
-        ),
+    let run_function = match receipt.inner {
+        Ok(ReceiptContent::RunFunction(run_function)) => run_function,
         Ok(_) => bail!("Expected a `RunFunction` receipt, but got something else."),
 
+        Err(ReceiptError::RunFunctionFailed {
+            error,
+            gas_used,
+            logs,
+        }) => {
+            if let Some(spinner) = spinner.as_mut() {
+                spinner.stop_with_symbol("Failed");
+            }
+            info!("Gas used: {}", gas_used);
+            if !trace {
+                print_logs(logs);
+            }
+            bail_user_error!("{error}")
+        }
         Err(err) => bail_user_error!("{err}"),
     };
 
+    let ReceiptRunFunction {
+        status_code,
+        headers,
+        body,
+        gas_used,
+        kv_writes,
+        kv_write_bytes,
+        logs,
+    } = run_function;
+
     if let Some(spinner) = spinner.as_mut() {
         spinner.stop_with_symbol(&format!("Status code: {}", status_code));
     } else {
@@ -155,11 +181,25 @@ pub async fn exec(
         info!("Body: {}", String::from_utf8_lossy(&body));
     }
 
+    info!("Gas used: {}", gas_used);
+    info!("KV writes: {} ({} bytes)", kv_writes, kv_write_bytes);
+
+    // When tracing, the logs have already been streamed
+    if !trace {
+        print_logs(logs);
+    }
+
     cfg.save()?;
 
     Ok(())
 }
 
+fn print_logs(logs: Vec<LogRecord>) {
+    for LogRecord { level, text, .. } in logs {
+        info!("[{}]: {}", level.symbol(), text);
+    }
+}
+
 async fn spawn_trace(address: &Address, jstz_client: &JstzClient) -> Result<()> {
     let event_source = jstz_client.logs_stream(address);
     // need to use mpsc instead of oneshot because of the loop
@@ -6,6 +6,8 @@ pub enum KvError {
     DowncastFailed,
     TransactionStackEmpty,
     ExpectedLookupMapEntry,
+    Conflict,
+    ParentMismatch,
 }
 
 #[derive(Display, Debug, Error, From)]
@@ -27,29 +29,34 @@ pub enum Error {
     },
 }
 
-impl From<Error> for JsError {
-    fn from(value: Error) -> Self {
-        match value {
-            Error::KvError { source } => JsNativeError::eval()
-                .with_message(format!("KvError: {}", source))
-                .into(),
-            Error::HostError { source } => JsNativeError::eval()
-                .with_message(format!("HostError: {}", source))
-                .into(),
-            Error::PathError { source } => JsNativeError::eval()
-                .with_message(format!("PathError: {}", source))
-                .into(),
+impl Error {
+    /// The native error thrown into JavaScript for this error
+    pub fn to_js_native_error(&self) -> JsNativeError {
+        match self {
+            Error::KvError { source } => {
+                JsNativeError::eval().with_message(format!("KvError: {}", source))
+            }
+            Error::HostError { source } => {
+                JsNativeError::eval().with_message(format!("HostError: {}", source))
+            }
+            Error::PathError { source } => {
+                JsNativeError::eval().with_message(format!("PathError: {}", source))
+            }
             Error::JsError { source } => JsNativeError::eval()
                 .with_message("JsError")
-                .with_cause(source)
-                .into(),
+                .with_cause(source.clone()),
             Error::SerializationError { description } => JsNativeError::eval()
-                .with_message(format!("serialization error: {description}"))
-                .into(),
+                .with_message(format!("serialization error: {description}")),
         }
     }
 }
 
+impl From<Error> for JsError {
+    fn from(value: Error) -> Self {
+        value.to_js_native_error().into()
+    }
+}
+
 impl From<boa_engine::JsNativeError> for Error {
     fn from(source: boa_engine::JsNativeError) -> Self {
         Error::JsError {
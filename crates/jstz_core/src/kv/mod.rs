@@ -12,7 +12,9 @@ use crate::error::Result;
 pub mod transaction;
 pub mod value;
 
-pub use transaction::{Entry, JsTransaction, Transaction};
+pub use transaction::{
+    Entry, JsTransaction, SuspendedTransaction, Transaction, WriteStats,
+};
 pub use value::Value;
 
 /// A transactional key-value store using an optimistic concurrency control scheme.
@@ -8,7 +8,7 @@ use derive_more::{Deref, DerefMut};
 use serde::de::DeserializeOwned;
 use tezos_smart_rollup_host::{path::OwnedPath, runtime::Runtime};
 
-use super::value::{BoxedValue, Value};
+use super::value::{self, BoxedValue, Value};
 use super::Storage;
 use crate::error::{KvError, Result};
 
@@ -40,7 +40,17 @@ use crate::error::{KvError, Result};
 /// +---------------+
 /// ```
 ///
-/// NOTE: Current implementation does NOT support concurrent transactions
+/// Conflicts are detected between sibling nested transactions. Each snapshot
+/// records the keys it reads from its enclosing snapshots (or the persistent
+/// store), and each write to a snapshot is versioned. A nested transaction
+/// fails to commit if its parent was written to (e.g. by a sibling transaction
+/// that committed first) at a key it read, after it began. Sibling
+/// transactions can be interleaved (on a single thread) by setting one aside
+/// with [`Transaction::suspend`] and later continuing it with
+/// [`Transaction::resume`].
+///
+/// NOTE: The root transaction is not validated against the persistent store,
+/// so independent root transactions must not run concurrently
 
 /// A key is a path in durable storage
 pub type Key = OwnedPath;
@@ -55,6 +65,8 @@ pub struct Transaction {
     // A stack of transactional snapshots
     stack: Vec<Snapshot>,
     lookup_map: LookupMap,
+    // The id of the next snapshot to begin
+    next_snapshot_id: u64,
 }
 
 #[derive(Debug, Clone, Deref, DerefMut)]
@@ -101,19 +113,96 @@ struct Snapshot {
     insert_edits: BTreeMap<Key, SnapshotValue>,
     // A set of 'remove' edits to be applied
     remove_edits: BTreeSet<Key>,
+    // A queue of (serialized) outbox messages to be written on commit
+    outbox_queue: Vec<Vec<u8>>,
+    // The keys of 'insert' edits that only cache a value read from storage
+    cached_reads: BTreeSet<Key>,
+    // The keys read from enclosing snapshots or from storage
+    read_set: BTreeSet<Key>,
+    // Identifies the snapshot among all snapshots of the transaction
+    id: u64,
+    // The version of the parent snapshot when this snapshot began
+    base_version: u64,
+    // The versions at which keys were written in this snapshot
+    write_log: WriteLog,
+}
+
+#[derive(Debug, Default)]
+struct WriteLog {
+    // Incremented on every write
+    version: u64,
+    // The version of the last write of each key
+    versions: BTreeMap<Key, u64>,
+}
+
+impl WriteLog {
+    fn record(&mut self, key: &Key) {
+        self.version += 1;
+        self.versions.insert(key.clone(), self.version);
+    }
+
+    fn written_since(&self, key: &Key, version: u64) -> bool {
+        self.versions.get(key).is_some_and(|&v| v > version)
+    }
+}
+
+/// A nested transaction set aside by [`Transaction::suspend`], which must be
+/// resumed on top of the same parent transaction.
+#[derive(Debug)]
+pub struct SuspendedTransaction {
+    snapshot: Snapshot,
+    // The depth, id and version of the parent snapshot when suspended
+    parent_depth: usize,
+    parent_id: u64,
+    parent_version: u64,
+}
+
+/// Writes buffered in a snapshot
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteStats {
+    /// Number of inserted or removed keys
+    pub count: usize,
+    /// Total size (in bytes) of the serialized inserted values
+    pub bytes: usize,
 }
 
 impl Snapshot {
+    fn new(id: u64, base_version: u64) -> Self {
+        Self {
+            id,
+            base_version,
+            ..Self::default()
+        }
+    }
+
     pub fn insert(&mut self, key: Key, value: SnapshotValue) {
+        self.write_log.record(&key);
         self.remove_edits.remove(&key);
+        self.cached_reads.remove(&key);
         self.insert_edits.insert(key, value);
     }
 
+    pub fn cache_read(&mut self, key: Key, value: SnapshotValue) {
+        self.remove_edits.remove(&key);
+        self.insert_edits.insert(key.clone(), value);
+        self.cached_reads.insert(key);
+    }
+
     pub fn remove(&mut self, key: Key) {
+        self.write_log.record(&key);
         self.insert_edits.remove(&key);
+        self.cached_reads.remove(&key);
         self.remove_edits.insert(key);
     }
 
+    /// Returns the keys written in this snapshot (excluding cached reads)
+    fn written_keys(&self) -> impl Iterator<Item = &Key> {
+        self.insert_edits
+            .keys()
+            .filter(|key| !self.cached_reads.contains(*key))
+            .chain(self.remove_edits.iter())
+    }
+
     pub fn lookup(&self, key: &Key) -> Option<&SnapshotValue> {
         if self.remove_edits.contains(key) {
             return None;
@@ -189,6 +278,18 @@ impl Transaction {
         Ok(())
     }
 
+    /// Cache a value read from storage in the current snapshot. Unlike an
+    /// 'insert' edit, a cached read does not count as a write.
+    fn current_snapshot_cache_read(
+        &mut self,
+        key: Key,
+        value: SnapshotValue,
+    ) -> Result<()> {
+        self.update_lookup_map(key.clone());
+        self.current_snapshot()?.cache_read(key, value);
+        Ok(())
+    }
+
     /// Lookup a key in the current snapshot
     fn current_snapshot_lookup(&mut self, key: &Key) -> Result<Option<&SnapshotValue>> {
         Ok(self.current_snapshot()?.lookup(key))
@@ -209,21 +310,42 @@ impl Transaction {
         Ok(())
     }
 
+    /// Record a read of a key from an enclosing snapshot or from storage in
+    /// the current snapshot's read set
+    fn current_snapshot_record_read(&mut self, key: &Key) -> Result<()> {
+        let snapshot = self.current_snapshot()?;
+        if !snapshot.read_set.contains(key) {
+            snapshot.read_set.insert(key.clone());
+        }
+        Ok(())
+    }
+
+    /// Returns the index of the snapshot holding the most recent edit of the key
+    fn lookup_snapshot_idx(&self, key: &Key) -> Option<usize> {
+        self.lookup_map
+            .get(key)
+            .and_then(|history| history.last())
+            .copied()
+    }
+
     fn lookup<V>(&mut self, rt: &impl Runtime, key: Key) -> Result<Option<&SnapshotValue>>
     where
         V: Value + DeserializeOwned,
     {
-        if let Some(&snapshot_idx) =
-            self.lookup_map.get(&key).and_then(|history| history.last())
-        {
+        if let Some(snapshot_idx) = self.lookup_snapshot_idx(&key) {
+            if snapshot_idx != self.current_snapshot_idx() {
+                self.current_snapshot_record_read(&key)?;
+            }
+
             let snapshot = &self.stack[snapshot_idx];
 
             return Ok(snapshot.lookup(&key));
         }
 
+        self.current_snapshot_record_read(&key)?;
         if let Some(value) = Storage::get::<V>(rt, &key)? {
             // TODO: This clone is probably not necessary
-            self.current_snapshot_insert(key.clone(), SnapshotValue::new(value))?;
+            self.current_snapshot_cache_read(key.clone(), SnapshotValue::new(value))?;
 
             self.current_snapshot_lookup(&key)
         } else {
@@ -239,9 +361,11 @@ impl Transaction {
     where
         V: Value + DeserializeOwned,
     {
-        if let Some(&snapshot_idx) =
-            self.lookup_map.get(&key).and_then(|history| history.last())
-        {
+        if let Some(snapshot_idx) = self.lookup_snapshot_idx(&key) {
+            if snapshot_idx != self.current_snapshot_idx() {
+                self.current_snapshot_record_read(&key)?;
+            }
+
             let snapshot = &self.stack[snapshot_idx];
 
             if let Some(value) = snapshot.lookup(&key) {
@@ -250,11 +374,14 @@ impl Transaction {
             } else {
                 Ok(None)
             }
-        } else if let Some(value) = Storage::get::<V>(rt, &key)? {
-            self.current_snapshot_insert(key.clone(), SnapshotValue::new(value))?;
-            self.current_snapshot_lookup_mut(&key)
         } else {
-            Ok(None)
+            self.current_snapshot_record_read(&key)?;
+            if let Some(value) = Storage::get::<V>(rt, &key)? {
+                self.current_snapshot_insert(key.clone(), SnapshotValue::new(value))?;
+                self.current_snapshot_lookup_mut(&key)
+            } else {
+                Ok(None)
+            }
         }
     }
 
@@ -280,15 +407,18 @@ impl Transaction {
 
     /// Returns `true` if the key-value store contains a key-value pair for the
     /// specified key.
-    pub fn contains_key(&self, rt: &impl Runtime, key: &Key) -> Result<bool> {
-        if let Some(&context_idx) =
-            self.lookup_map.get(key).and_then(|history| history.last())
-        {
+    pub fn contains_key(&mut self, rt: &impl Runtime, key: &Key) -> Result<bool> {
+        if let Some(context_idx) = self.lookup_snapshot_idx(key) {
+            if context_idx != self.current_snapshot_idx() {
+                self.current_snapshot_record_read(key)?;
+            }
+
             let context = &self.stack[context_idx];
 
             return Ok(context.contains_key(key));
         }
 
+        self.current_snapshot_record_read(key)?;
         Storage::contains_key(rt, key)
     }
 
@@ -331,26 +461,98 @@ impl Transaction {
                 inner,
                 &mut self.lookup_map,
                 current_snapshot_idx,
+                &mut current_snapshot.write_log,
             )),
-            btree_map::Entry::Occupied(inner) => {
-                Ok(Entry::occupied(inner, &mut current_snapshot.remove_edits))
+            btree_map::Entry::Occupied(inner) => Ok(Entry::occupied(
+                inner,
+                &mut current_snapshot.remove_edits,
+                &mut current_snapshot.write_log,
+            )),
+        }
+    }
+
+    /// Queue an outbox message in the current snapshot. Queued messages are
+    /// written to the rollup outbox when the root transaction is committed and
+    /// are discarded if the transaction is rolled back.
+    pub fn queue_outbox_message(&mut self, message: Vec<u8>) -> Result<()> {
+        self.current_snapshot()?.outbox_queue.push(message);
+        Ok(())
+    }
+
+    /// Returns the writes buffered in the current snapshot, excluding values
+    /// that were only read.
+    pub fn current_writes(&mut self) -> Result<WriteStats> {
+        let snapshot = self.current_snapshot()?;
+
+        let mut bytes = 0;
+        for (key, value) in &snapshot.insert_edits {
+            if !snapshot.cached_reads.contains(key) {
+                bytes += value::serialize(value.0.as_ref())?.len();
             }
         }
+
+        Ok(WriteStats {
+            count: snapshot.insert_edits.len() - snapshot.cached_reads.len()
+                + snapshot.remove_edits.len(),
+            bytes,
+        })
     }
 
     /// Begin a transaction.
     pub fn begin(&mut self) {
-        self.stack.push(Snapshot::default())
+        let base_version = self
+            .stack
+            .last()
+            .map(|snapshot| snapshot.write_log.version)
+            .unwrap_or_default();
+
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+
+        self.stack.push(Snapshot::new(id, base_version))
+    }
+
+    /// Validate a nested transaction against its parent, returning
+    /// `KvError::Conflict` if the parent was written to at a key the
+    /// transaction read, after the transaction began.
+    pub fn validate(&self) -> Result<()> {
+        let [.., prev_ctxt, curr_ctxt] = self.stack.as_slice() else {
+            return Ok(());
+        };
+
+        let is_conflicting = curr_ctxt.read_set.iter().any(|key| {
+            prev_ctxt
+                .write_log
+                .written_since(key, curr_ctxt.base_version)
+        });
+
+        if is_conflicting {
+            return Err(KvError::Conflict.into());
+        }
+
+        Ok(())
     }
 
     /// Commit a transaction.
+    ///
+    /// Fails with `KvError::Conflict` if the transaction fails validation, in
+    /// which case the transaction remains active and should be rolled back.
     pub fn commit(&mut self, rt: &mut impl Runtime) -> Result<()> {
+        self.validate()?;
+
         let curr_ctxt = self.stack.pop().ok_or(KvError::TransactionStackEmpty)?;
 
         // Following the `.pop`, `prev_idx` is the index of prev_idx (if it exists)
         let prev_idx = self.current_snapshot_idx();
 
         if let Some(prev_ctxt) = self.stack.last_mut() {
+            // Reads of keys not written by the parent are reads of the parent
+            for key in curr_ctxt.read_set {
+                if !prev_ctxt.write_log.versions.contains_key(&key) {
+                    prev_ctxt.read_set.insert(key);
+                }
+            }
+
             // TODO: These clones are probably uncessary since the entry of btree will always be occupied.
             for key in curr_ctxt.remove_edits {
                 self.lookup_map.update(key.clone(), prev_idx);
@@ -359,8 +561,14 @@ impl Transaction {
 
             for (key, value) in curr_ctxt.insert_edits {
                 self.lookup_map.update(key.clone(), prev_idx);
-                prev_ctxt.insert(key, value);
+                if curr_ctxt.cached_reads.contains(&key) {
+                    prev_ctxt.cache_read(key, value);
+                } else {
+                    prev_ctxt.insert(key, value);
+                }
             }
+
+            prev_ctxt.outbox_queue.extend(curr_ctxt.outbox_queue);
         } else {
             for key in &curr_ctxt.remove_edits {
                 Storage::remove(rt, key)?
@@ -370,6 +578,11 @@ impl Transaction {
                 Storage::insert(rt, &key, value.0.as_ref())?
             }
 
+            // Outbox messages are flushed after the storage writes
+            for message in curr_ctxt.outbox_queue {
+                rt.write_output(&message)?
+            }
+
             // Update lookup map
             self.lookup_map.clear()
         }
@@ -393,6 +606,64 @@ impl Transaction {
 
         Ok(())
     }
+
+    /// Set aside the current (nested) transaction, such that a sibling
+    /// transaction may begin. The suspended transaction is validated against
+    /// the writes of its siblings when it is resumed and committed.
+    pub fn suspend(&mut self) -> Result<SuspendedTransaction> {
+        if self.stack.len() < 2 {
+            return Err(KvError::TransactionStackEmpty.into());
+        }
+
+        let curr_ctxt = self.stack.pop().ok_or(KvError::TransactionStackEmpty)?;
+
+        for key in curr_ctxt
+            .remove_edits
+            .iter()
+            .chain(curr_ctxt.insert_edits.keys())
+        {
+            self.lookup_map.rollback(key)?;
+        }
+
+        let parent = self.stack.last().ok_or(KvError::TransactionStackEmpty)?;
+
+        Ok(SuspendedTransaction {
+            snapshot: curr_ctxt,
+            parent_depth: self.stack.len(),
+            parent_id: parent.id,
+            parent_version: parent.write_log.version,
+        })
+    }
+
+    /// Resume a suspended transaction as the current transaction.
+    ///
+    /// Fails with `KvError::ParentMismatch` if the current transaction is not
+    /// the one the transaction was suspended from.
+    pub fn resume(&mut self, suspended: SuspendedTransaction) -> Result<()> {
+        // Writes to a snapshot only ever increase its version
+        let is_same_parent = self.stack.len() == suspended.parent_depth
+            && self.stack.last().is_some_and(|parent| {
+                parent.id == suspended.parent_id
+                    && parent.write_log.version >= suspended.parent_version
+            });
+        if !is_same_parent {
+            return Err(KvError::ParentMismatch.into());
+        }
+
+        let snapshot = suspended.snapshot;
+        let idx = self.stack.len();
+
+        for key in snapshot
+            .remove_edits
+            .iter()
+            .chain(snapshot.insert_edits.keys())
+        {
+            self.lookup_map.update(key.clone(), idx);
+        }
+
+        self.stack.push(snapshot);
+        Ok(())
+    }
 }
 
 /// A view into a single entry in the transaction snapshot, which is either
@@ -410,11 +681,13 @@ impl<'a, V> Entry<'a, V> {
         inner: btree_map::VacantEntry<'a, Key, SnapshotValue>,
         lookup_map: &'a mut LookupMap,
         snapshot_idx: usize,
+        write_log: &'a mut WriteLog,
     ) -> Self {
         Entry::Vacant(VacantEntry {
             inner,
             lookup_map,
             snapshot_idx,
+            write_log,
             _marker: PhantomData,
         })
     }
@@ -422,10 +695,12 @@ impl<'a, V> Entry<'a, V> {
     fn occupied(
         inner: btree_map::OccupiedEntry<'a, Key, SnapshotValue>,
         remove_edits: &'a mut BTreeSet<Key>,
+        write_log: &'a mut WriteLog,
     ) -> Self {
         Entry::Occupied(OccupiedEntry {
             inner,
             remove_edits,
+            write_log,
             _marker: PhantomData,
         })
     }
@@ -447,6 +722,8 @@ pub struct VacantEntry<'a, V: 'a> {
     // Reference to lookup map (if we insert into the vacant entry)
     lookup_map: &'a mut LookupMap,
     snapshot_idx: usize,
+    // Reference to the write log of the current snapshot
+    write_log: &'a mut WriteLog,
     _marker: PhantomData<V>,
 }
 
@@ -469,6 +746,7 @@ impl<'a, V: 'a> VacantEntry<'a, V> {
     {
         self.lookup_map
             .update(self.key().clone(), self.snapshot_idx);
+        self.write_log.record(self.inner.key());
         self.inner
             .insert(SnapshotValue::new(value))
             .as_mut()
@@ -482,6 +760,8 @@ pub struct OccupiedEntry<'a, V: 'a> {
     inner: btree_map::OccupiedEntry<'a, Key, SnapshotValue>,
     // Reference to the set of keys to be removed from the current snapshot
     remove_edits: &'a mut BTreeSet<Key>,
+    // Reference to the write log of the current snapshot
+    write_log: &'a mut WriteLog,
     _marker: PhantomData<V>,
 }
 
@@ -498,6 +778,7 @@ impl<'a, V> OccupiedEntry<'a, V> {
         V: Value,
     {
         let (key, entry) = self.inner.remove_entry();
+        self.write_log.record(&key);
         self.remove_edits.insert(key.clone());
         (key, entry.into_value().expect("Invalid type id invariant"))
     }
@@ -548,6 +829,7 @@ impl<'a, V> OccupiedEntry<'a, V> {
     where
         V: Value,
     {
+        self.write_log.record(self.inner.key());
         self.remove_edits.insert(self.key().clone());
         self.inner
             .remove()
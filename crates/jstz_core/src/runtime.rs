@@ -1,5 +1,5 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::VecDeque,
     future::poll_fn,
     io::Read,
@@ -27,6 +27,17 @@ use crate::{
 // This is the unix timestamp for date 31-07-2023 10:50:26 -- the date of the first commit
 const UTC_NOW: i64 = 1690797026;
 
+thread_local! {
+    /// The unix timestamp returned by `Date.now()`
+    static JS_UTC_NOW: Cell<i64> = Cell::new(UTC_NOW);
+}
+
+/// Sets the unix timestamp (in seconds) observed by `Date`. Hosts must set
+/// this to a value agreed upon by all nodes to keep execution deterministic.
+pub fn set_utc_now(timestamp: i64) {
+    JS_UTC_NOW.with(|utc_now| utc_now.set(timestamp))
+}
+
 struct Hooks;
 
 impl HostHooks for Hooks {
@@ -49,8 +60,8 @@ impl HostHooks for Hooks {
     // }
 
     fn utc_now(&self) -> NaiveDateTime {
-        NaiveDateTime::from_timestamp_opt(UTC_NOW, 0)
-            .expect("Failed to create `NaiveDateTime` from `UTC_NOW`")
+        NaiveDateTime::from_timestamp_opt(JS_UTC_NOW.with(Cell::get), 0)
+            .expect("Failed to create `NaiveDateTime` from `JS_UTC_NOW`")
     }
 
     fn local_from_utc(&self, utc: NaiveDateTime) -> DateTime<FixedOffset> {
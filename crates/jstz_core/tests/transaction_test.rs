@@ -1,10 +1,16 @@
 #[cfg(test)]
 mod test {
 
-    use jstz_core::{error::Result, host::HostRuntime, kv, kv::transaction::Transaction};
+    use jstz_core::{
+        error::{Error, KvError, Result},
+        host::HostRuntime,
+        kv,
+        kv::transaction::{Transaction, WriteStats},
+    };
     use jstz_crypto::keypair_from_passphrase;
     use jstz_crypto::public_key_hash::PublicKeyHash;
     use jstz_proto::context::account::Account;
+    use tezos_smart_rollup_host::path::OwnedPath;
     use tezos_smart_rollup_mock::MockHost;
 
     fn get_random_public_key_hash(passphrase: &str) -> PublicKeyHash {
@@ -107,4 +113,113 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_current_writes_exclude_reads() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let read_key = OwnedPath::try_from("/read".to_string()).unwrap();
+        let write_key = OwnedPath::try_from("/write".to_string()).unwrap();
+        kv::Storage::insert(hrt, &read_key, &1u64)?;
+
+        let mut tx = Transaction::default();
+        tx.begin();
+        tx.begin();
+
+        assert_eq!(tx.get::<u64>(hrt, read_key)?, Some(&1));
+        assert_eq!(tx.current_writes()?, WriteStats::default());
+
+        tx.insert(write_key, 2u64)?;
+        tx.commit(hrt)?;
+
+        assert_eq!(tx.current_writes()?, WriteStats { count: 1, bytes: 8 });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sibling_transactions_conflict() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let key = OwnedPath::try_from("/counter".to_string()).unwrap();
+        let other_key = OwnedPath::try_from("/other".to_string()).unwrap();
+        kv::Storage::insert(hrt, &key, &0u64)?;
+
+        let mut tx = Transaction::default();
+        tx.begin();
+
+        // t1 reads the counter, t2 only reads an unrelated key
+        tx.begin();
+        let counter = *tx.get::<u64>(hrt, key.clone())?.unwrap();
+        let t1 = tx.suspend()?;
+
+        tx.begin();
+        assert_eq!(tx.get::<u64>(hrt, other_key.clone())?, None);
+        let t2 = tx.suspend()?;
+
+        // t3 increments the counter and commits first
+        tx.begin();
+        *tx.get_mut::<u64>(hrt, key.clone())?.unwrap() += 1;
+        tx.commit(hrt)?;
+
+        // t1 read a stale counter, so it fails validation
+        tx.resume(t1)?;
+        tx.insert(key.clone(), counter + 10)?;
+        assert!(matches!(
+            tx.commit(hrt),
+            Err(Error::KvError {
+                source: KvError::Conflict
+            })
+        ));
+        tx.rollback()?;
+
+        // t2 did not read anything written by its siblings
+        tx.resume(t2)?;
+        tx.insert(other_key.clone(), 1u64)?;
+        tx.commit(hrt)?;
+
+        tx.commit(hrt)?;
+
+        assert_eq!(kv::Storage::get::<u64>(hrt, &key)?, Some(1));
+        assert_eq!(kv::Storage::get::<u64>(hrt, &other_key)?, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_requires_same_parent() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let key = OwnedPath::try_from("/counter".to_string()).unwrap();
+
+        let mut tx = Transaction::default();
+        tx.begin();
+        tx.begin();
+        tx.insert(key.clone(), 1u64)?;
+        let t1 = tx.suspend()?;
+
+        // A different parent at the same depth
+        tx.rollback()?;
+        tx.begin();
+        assert!(matches!(
+            tx.resume(t1),
+            Err(Error::KvError {
+                source: KvError::ParentMismatch
+            })
+        ));
+
+        // The parent at a different depth
+        tx.begin();
+        let t2 = tx.suspend()?;
+        tx.begin();
+        assert!(matches!(
+            tx.resume(t2),
+            Err(Error::KvError {
+                source: KvError::ParentMismatch
+            })
+        ));
+        tx.rollback()?;
+        tx.commit(hrt)?;
+
+        assert_eq!(kv::Storage::get::<u64>(hrt, &key)?, None);
+
+        Ok(())
+    }
 }
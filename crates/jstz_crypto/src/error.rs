@@ -8,6 +8,9 @@ pub enum Error {
     TezosFromBytesError { source: FromBytesError },
     TezosCryptoError { source: CryptoError },
     InvalidSignature,
+    InvalidHash,
+    InvalidSecretKey,
+    InvalidCurve,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
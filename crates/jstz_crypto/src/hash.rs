@@ -1,5 +1,7 @@
 use boa_gc::{empty_trace, Finalize, Trace};
 use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
 #[derive(
     Debug,
     Clone,
@@ -49,4 +51,35 @@ impl Blake2b {
     pub fn as_array(&self) -> &[u8; 32] {
         &self.0
     }
+
+    /// Parses a hash from its hex representation (see `to_string`)
+    pub fn from_hex(data: &str) -> Result<Self> {
+        let bytes = hex::decode(data).map_err(|_| Error::InvalidHash)?;
+        let digest = bytes.try_into().map_err(|_| Error::InvalidHash)?;
+        Ok(Self(digest))
+    }
+}
+
+/// Serializes a [`Blake2b`] as its hex representation, for use with
+/// `#[serde(with = "jstz_crypto::hash::hex_string")]`. Hashes shown to
+/// clients (e.g. ticket hashes in receipts) use the same representation
+/// accepted by the JS APIs.
+pub mod hex_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::Blake2b;
+
+    pub fn serialize<S: Serializer>(
+        hash: &Blake2b,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hash.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Blake2b, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Blake2b::from_hex(&hex).map_err(de::Error::custom)
+    }
 }
@@ -7,20 +7,75 @@ pub mod public_key_hash;
 pub mod secret_key;
 pub mod signature;
 
-use tezos_crypto_rs::hash::SeedEd25519;
+use std::str::FromStr;
+
+use tezos_crypto_rs::hash::{PublicKeyP256, PublicKeySecp256k1, SeedEd25519};
 
 use crate::{hash::Blake2b, public_key::PublicKey, secret_key::SecretKey};
 
+/// The elliptic curves of Tezos keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Curve {
+    /// tz1 addresses
+    #[default]
+    Ed25519,
+    /// tz2 addresses
+    Secp256k1,
+    /// tz3 addresses
+    P256,
+}
+
+impl FromStr for Curve {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ed25519" => Ok(Curve::Ed25519),
+            "secp256k1" => Ok(Curve::Secp256k1),
+            "p256" => Ok(Curve::P256),
+            _ => Err(Error::InvalidCurve),
+        }
+    }
+}
+
 pub fn keypair_from_passphrase(passphrase: &str) -> Result<(SecretKey, PublicKey)> {
+    keypair_from_passphrase_and_curve(passphrase, Curve::Ed25519)
+}
+
+pub fn keypair_from_passphrase_and_curve(
+    passphrase: &str,
+    curve: Curve,
+) -> Result<(SecretKey, PublicKey)> {
     let ikm = Blake2b::from(passphrase.as_bytes()).as_array().to_vec();
-    let seed = SeedEd25519::try_from(ikm)?;
-    let (pk, sk) = seed.keypair()?;
-    Ok((SecretKey::Ed25519(sk), PublicKey::Ed25519(pk)))
+    match curve {
+        Curve::Ed25519 => {
+            let seed = SeedEd25519::try_from(ikm)?;
+            let (pk, sk) = seed.keypair()?;
+            Ok((SecretKey::Ed25519(sk), PublicKey::Ed25519(pk)))
+        }
+        Curve::Secp256k1 => {
+            let sk = libsecp256k1::SecretKey::parse_slice(&ikm)
+                .map_err(|_| Error::InvalidSecretKey)?;
+            let pk = libsecp256k1::PublicKey::from_secret_key(&sk);
+            let pk = PublicKeySecp256k1::try_from(pk.serialize_compressed().to_vec())?;
+            Ok((SecretKey::Secp256k1(sk), PublicKey::Secp256k1(pk)))
+        }
+        Curve::P256 => {
+            let sk = p256::ecdsa::SigningKey::from_slice(&ikm)
+                .map_err(|_| Error::InvalidSecretKey)?;
+            let pk = sk.verifying_key().to_encoded_point(true);
+            let pk = PublicKeyP256::try_from(pk.as_bytes().to_vec())?;
+            Ok((SecretKey::P256(sk), PublicKey::P256(pk)))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::keypair_from_passphrase;
+    use super::{keypair_from_passphrase, keypair_from_passphrase_and_curve, Curve};
+    use crate::{
+        public_key::PublicKey, public_key_hash::PublicKeyHash, secret_key::SecretKey,
+    };
     use proptest::prelude::*;
 
     proptest! {
@@ -30,5 +85,30 @@ mod tests {
             let sig = sk.sign(&message).unwrap();
             assert!(sig.verify(&pk, &message).is_ok());
         }
+
+        #[test]
+        fn test_keygen_verify_curves(passphrase in any::<String>(), message in any::<Vec<u8>>()) {
+            for curve in [Curve::Secp256k1, Curve::P256] {
+                let (sk, pk) = keypair_from_passphrase_and_curve(&passphrase, curve).unwrap();
+                let sig = sk.sign(&message).unwrap();
+                assert!(sig.verify(&pk, &message).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_address_prefixes() {
+        for (curve, prefix) in [
+            (Curve::Ed25519, "tz1"),
+            (Curve::Secp256k1, "tz2"),
+            (Curve::P256, "tz3"),
+        ] {
+            let (sk, pk) = keypair_from_passphrase_and_curve("jstz", curve).unwrap();
+            let pkh = PublicKeyHash::try_from(&pk).unwrap();
+            assert!(pkh.to_base58().starts_with(prefix));
+            assert_eq!(PublicKeyHash::from_base58(&pkh.to_base58()).unwrap(), pkh);
+            assert_eq!(PublicKey::from_base58(&pk.to_base58()).unwrap(), pk);
+            assert_eq!(SecretKey::from_base58(&sk.to_base58()).unwrap(), sk);
+        }
     }
 }
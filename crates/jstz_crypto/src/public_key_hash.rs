@@ -4,7 +4,7 @@ use boa_gc::{empty_trace, Finalize, Trace};
 use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::{
     blake2b::digest,
-    hash::{ContractTz1Hash, HashTrait},
+    hash::{ContractTz1Hash, ContractTz2Hash, ContractTz3Hash, HashTrait},
     PublicKeyWithHash,
 };
 
@@ -18,6 +18,8 @@ use crate::{
 )]
 pub enum PublicKeyHash {
     Tz1(ContractTz1Hash),
+    Tz2(ContractTz2Hash),
+    Tz3(ContractTz3Hash),
 }
 
 unsafe impl Trace for PublicKeyHash {
@@ -34,23 +36,39 @@ impl FromStr for PublicKeyHash {
 
 impl PublicKeyHash {
     pub fn to_base58(&self) -> String {
-        let PublicKeyHash::Tz1(tz1) = self;
-        tz1.to_base58_check()
+        match self {
+            PublicKeyHash::Tz1(tz1) => tz1.to_base58_check(),
+            PublicKeyHash::Tz2(tz2) => tz2.to_base58_check(),
+            PublicKeyHash::Tz3(tz3) => tz3.to_base58_check(),
+        }
     }
 
     pub fn from_base58(data: &str) -> Result<Self> {
-        let tz1 = ContractTz1Hash::from_base58_check(data)?;
-        Ok(PublicKeyHash::Tz1(tz1))
+        match data.get(..3) {
+            Some("tz2") => Ok(PublicKeyHash::Tz2(ContractTz2Hash::from_base58_check(
+                data,
+            )?)),
+            Some("tz3") => Ok(PublicKeyHash::Tz3(ContractTz3Hash::from_base58_check(
+                data,
+            )?)),
+            _ => Ok(PublicKeyHash::Tz1(ContractTz1Hash::from_base58_check(
+                data,
+            )?)),
+        }
     }
 
+    /// Builds a tz1 public key hash from raw bytes
     pub fn from_slice(bytes: &[u8]) -> Result<Self> {
         let tz1 = ContractTz1Hash::try_from_bytes(bytes)?;
         Ok(PublicKeyHash::Tz1(tz1))
     }
 
     pub fn as_bytes(&self) -> &[u8] {
-        let PublicKeyHash::Tz1(tz1) = self;
-        tz1.as_ref()
+        match self {
+            PublicKeyHash::Tz1(tz1) => tz1.as_ref(),
+            PublicKeyHash::Tz2(tz2) => tz2.as_ref(),
+            PublicKeyHash::Tz3(tz3) => tz3.as_ref(),
+        }
     }
     pub fn digest(data: &[u8]) -> Result<Self> {
         let out_len = ContractTz1Hash::hash_size();
@@ -69,8 +87,10 @@ impl TryFrom<&PublicKey> for PublicKeyHash {
     type Error = Error;
 
     fn try_from(pk: &PublicKey) -> Result<Self> {
-        let PublicKey::Ed25519(key) = pk;
-        let tz1 = key.pk_hash();
-        Ok(PublicKeyHash::Tz1(tz1))
+        match pk {
+            PublicKey::Ed25519(key) => Ok(PublicKeyHash::Tz1(key.pk_hash())),
+            PublicKey::Secp256k1(key) => Ok(PublicKeyHash::Tz2(key.pk_hash())),
+            PublicKey::P256(key) => Ok(PublicKeyHash::Tz3(key.pk_hash())),
+        }
     }
 }
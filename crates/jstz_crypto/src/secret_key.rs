@@ -1,18 +1,32 @@
 use std::fmt::{self, Debug};
 
+use p256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey};
 use serde::{Deserialize, Serialize};
-use tezos_crypto_rs::hash::SecretKeyEd25519;
-use tezos_crypto_rs::hash::SeedEd25519;
+use tezos_crypto_rs::{
+    base58::{FromBase58Check, ToBase58Check},
+    hash::{Secp256k1Signature, SecretKeyEd25519, SeedEd25519},
+};
 
-use crate::{error::Result, signature::Signature};
+use crate::{
+    error::{Error, Result},
+    hash::Blake2b,
+    signature::Signature,
+};
 
 // FIXME: workaround via `SeedEd25519` will be unnecessary in the next tezos_crypto_rs release
 //        (will be included in next SDK release)
 
+/// Base58 prefix of secp256k1 secret keys (`spsk`)
+const SECP256K1_SECRET_KEY_PREFIX: [u8; 4] = [17, 162, 224, 201];
+/// Base58 prefix of P-256 secret keys (`p2sk`)
+const P256_SECRET_KEY_PREFIX: [u8; 4] = [16, 81, 238, 189];
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
-#[serde(from = "SecretKeySerde", into = "SecretKeySerde")]
+#[serde(try_from = "SecretKeySerde", into = "SecretKeySerde")]
 pub enum SecretKey {
     Ed25519(SecretKeyEd25519),
+    Secp256k1(libsecp256k1::SecretKey),
+    P256(SigningKey),
 }
 
 impl Debug for SecretKey {
@@ -21,21 +35,80 @@ impl Debug for SecretKey {
     }
 }
 
+fn to_prefixed_base58(prefix: &[u8], bytes: &[u8]) -> String {
+    [prefix, bytes].concat().to_base58check()
+}
+
+fn from_prefixed_base58(prefix: &[u8], data: &str) -> Result<Vec<u8>> {
+    let bytes = data.from_base58check()?;
+    match bytes.strip_prefix(prefix) {
+        Some(bytes) => Ok(bytes.to_vec()),
+        None => Err(Error::InvalidSecretKey),
+    }
+}
+
 impl SecretKey {
     pub fn to_base58(&self) -> String {
-        let Self::Ed25519(sk) = self;
-        sk.to_base58_check()
+        match self {
+            SecretKey::Ed25519(sk) => sk.to_base58_check(),
+            SecretKey::Secp256k1(sk) => {
+                to_prefixed_base58(&SECP256K1_SECRET_KEY_PREFIX, &sk.serialize())
+            }
+            SecretKey::P256(sk) => {
+                to_prefixed_base58(&P256_SECRET_KEY_PREFIX, &sk.to_bytes())
+            }
+        }
     }
 
     pub fn from_base58(data: &str) -> Result<Self> {
-        let sk = SecretKeyEd25519::from_base58_check(data)?;
+        match data.get(..4) {
+            Some("spsk") => {
+                let bytes = from_prefixed_base58(&SECP256K1_SECRET_KEY_PREFIX, data)?;
+                Self::secp256k1_from_bytes(&bytes)
+            }
+            Some("p2sk") => {
+                let bytes = from_prefixed_base58(&P256_SECRET_KEY_PREFIX, data)?;
+                Self::p256_from_bytes(&bytes)
+            }
+            _ => {
+                let sk = SecretKeyEd25519::from_base58_check(data)?;
+                Ok(SecretKey::Ed25519(sk))
+            }
+        }
+    }
 
-        Ok(SecretKey::Ed25519(sk))
+    pub fn secp256k1_from_bytes(bytes: &[u8]) -> Result<Self> {
+        let sk = libsecp256k1::SecretKey::parse_slice(bytes)
+            .map_err(|_| Error::InvalidSecretKey)?;
+        Ok(SecretKey::Secp256k1(sk))
     }
 
+    pub fn p256_from_bytes(bytes: &[u8]) -> Result<Self> {
+        let sk = SigningKey::from_slice(bytes).map_err(|_| Error::InvalidSecretKey)?;
+        Ok(SecretKey::P256(sk))
+    }
+
+    /// Signs the message. As in Tezos, secp256k1 and P-256 signatures are
+    /// computed over the Blake2b-256 digest of the message.
     pub fn sign(&self, message: impl AsRef<[u8]>) -> Result<Signature> {
-        let SecretKey::Ed25519(sk) = self;
-        Ok(Signature::Ed25519(sk.sign(message)?))
+        match self {
+            SecretKey::Ed25519(sk) => Ok(Signature::Ed25519(sk.sign(message)?)),
+            SecretKey::Secp256k1(sk) => {
+                let digest = Blake2b::from(message.as_ref());
+                let message = libsecp256k1::Message::parse(digest.as_array());
+                let (sig, _) = libsecp256k1::sign(&message, sk);
+                Ok(Signature::Secp256k1(Secp256k1Signature::try_from(
+                    sig.serialize().to_vec(),
+                )?))
+            }
+            SecretKey::P256(sk) => {
+                let digest = Blake2b::from(message.as_ref());
+                let sig: p256::ecdsa::Signature = sk
+                    .sign_prehash(digest.as_ref())
+                    .map_err(|_| Error::InvalidSignature)?;
+                Ok(Signature::P256(sig.to_bytes().to_vec().try_into()?))
+            }
+        }
     }
 }
 
@@ -48,20 +121,35 @@ impl ToString for SecretKey {
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 enum SecretKeySerde {
     Ed25519(SeedEd25519),
+    Secp256k1(String),
+    P256(String),
 }
 
 impl From<SecretKey> for SecretKeySerde {
     fn from(s: SecretKey) -> Self {
-        let SecretKey::Ed25519(sk) = s;
-        let sk: Vec<u8> = sk.into();
-        Self::Ed25519(SeedEd25519::try_from(sk).unwrap())
+        match s {
+            SecretKey::Ed25519(sk) => {
+                let sk: Vec<u8> = sk.into();
+                Self::Ed25519(SeedEd25519::try_from(sk).unwrap())
+            }
+            sk @ SecretKey::Secp256k1(_) => Self::Secp256k1(sk.to_base58()),
+            sk @ SecretKey::P256(_) => Self::P256(sk.to_base58()),
+        }
     }
 }
 
-impl From<SecretKeySerde> for SecretKey {
-    fn from(s: SecretKeySerde) -> Self {
-        let SecretKeySerde::Ed25519(sk) = s;
-        let sk: Vec<u8> = sk.into();
-        Self::Ed25519(SecretKeyEd25519::try_from(sk).unwrap())
+impl TryFrom<SecretKeySerde> for SecretKey {
+    type Error = Error;
+
+    fn try_from(s: SecretKeySerde) -> Result<Self> {
+        match s {
+            SecretKeySerde::Ed25519(sk) => {
+                let sk: Vec<u8> = sk.into();
+                Ok(Self::Ed25519(SecretKeyEd25519::try_from(sk)?))
+            }
+            SecretKeySerde::Secp256k1(sk) | SecretKeySerde::P256(sk) => {
+                Self::from_base58(&sk)
+            }
+        }
     }
 }
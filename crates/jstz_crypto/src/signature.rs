@@ -1,32 +1,47 @@
 use serde::{Deserialize, Serialize};
-use tezos_crypto_rs::PublicKeySignatureVerifier;
+use tezos_crypto_rs::{
+    hash::{Ed25519Signature, P256Signature, Secp256k1Signature},
+    PublicKeySignatureVerifier,
+};
 
 use crate::{public_key::PublicKey, Error, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Signature {
-    Ed25519(tezos_crypto_rs::hash::Ed25519Signature),
+    Ed25519(Ed25519Signature),
+    Secp256k1(Secp256k1Signature),
+    P256(P256Signature),
 }
 
 impl Signature {
     pub fn to_base58(&self) -> String {
         match self {
             Signature::Ed25519(sig) => sig.to_base58_check(),
+            Signature::Secp256k1(sig) => sig.to_base58_check(),
+            Signature::P256(sig) => sig.to_base58_check(),
         }
     }
 }
 
 impl Signature {
     pub fn verify(&self, public_key: &PublicKey, message: &[u8]) -> Result<()> {
-        match (self, public_key) {
+        let result = match (self, public_key) {
             (Signature::Ed25519(sig), PublicKey::Ed25519(pk)) => {
-                let result = pk.verify_signature(sig, message)?;
-                if result {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidSignature)
-                }
+                pk.verify_signature(sig, message)?
             }
+            (Signature::Secp256k1(sig), PublicKey::Secp256k1(pk)) => {
+                pk.verify_signature(sig, message)?
+            }
+            (Signature::P256(sig), PublicKey::P256(pk)) => {
+                pk.verify_signature(sig, message)?
+            }
+            _ => false,
+        };
+
+        if result {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
         }
     }
 }
@@ -0,0 +1,275 @@
+use jstz_core::kv::Storage;
+use jstz_crypto::hash::Blake2b;
+use jstz_proto::{
+    context::account::Address,
+    operation::{
+        chunk::{chunk_count, Chunk, ChunkHeader, MAX_OPERATION_SIZE},
+        SignedOperation,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tezos_smart_rollup::{
+    prelude::{debug_msg, Runtime},
+    storage::path::{self, OwnedPath, RefPath},
+};
+
+/// Number of levels after which an incomplete chunk set is discarded, unless
+/// overridden at `CHUNK_EXPIRY_PATH`
+pub const DEFAULT_CHUNK_EXPIRY: u32 = 20;
+pub const CHUNK_EXPIRY_PATH: RefPath = RefPath::assert_from(b"/jstz_chunk_expiry");
+
+/// Maximum number of incomplete chunk sets stored at once for each signer.
+/// New chunk sets of a signer are ignored until its pending ones complete or
+/// expire.
+pub const MAX_PENDING_CHUNK_SETS: u32 = 4;
+
+/// Pending chunk sets, each stored as `/<header hash>/pending` along with one
+/// `/<header hash>/<index>` entry per chunk received
+const CHUNKS_PATH: RefPath = RefPath::assert_from(b"/jstz_chunks");
+/// The header hashes of the pending chunk sets expiring at each level, stored
+/// as `/<level>`
+const CHUNKS_EXPIRING_PATH: RefPath = RefPath::assert_from(b"/jstz_chunks_expiring");
+/// The number of pending chunk sets of each signer, stored as `/<address>`
+const CHUNKS_USAGE_PATH: RefPath = RefPath::assert_from(b"/jstz_chunks_usage");
+
+/// A chunk set whose header has been received, but some of whose chunks are
+/// missing
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingChunks {
+    header: ChunkHeader,
+    signer: Address,
+    /// Number of chunks received
+    received: u32,
+    expires_at: u32,
+}
+
+fn set_path(hash: &Blake2b) -> Option<OwnedPath> {
+    let hash_path = OwnedPath::try_from(format!("/{}", hash.to_string())).ok()?;
+    path::concat(&CHUNKS_PATH, &hash_path).ok()
+}
+
+fn pending_path(hash: &Blake2b) -> Option<OwnedPath> {
+    path::concat(&set_path(hash)?, &RefPath::assert_from(b"/pending")).ok()
+}
+
+fn chunk_path(hash: &Blake2b, index: u32) -> Option<OwnedPath> {
+    let index_path = OwnedPath::try_from(format!("/{}", index)).ok()?;
+    path::concat(&set_path(hash)?, &index_path).ok()
+}
+
+fn expiring_path(level: u32) -> Option<OwnedPath> {
+    let level_path = OwnedPath::try_from(format!("/{}", level)).ok()?;
+    path::concat(&CHUNKS_EXPIRING_PATH, &level_path).ok()
+}
+
+fn usage_path(signer: &Address) -> Option<OwnedPath> {
+    let signer_path = OwnedPath::try_from(format!("/{}", signer)).ok()?;
+    path::concat(&CHUNKS_USAGE_PATH, &signer_path).ok()
+}
+
+fn read_usage(rt: &impl Runtime, signer: &Address) -> u32 {
+    usage_path(signer)
+        .and_then(|path| Storage::get(rt, &path).ok().flatten())
+        .unwrap_or_default()
+}
+
+fn write_usage(rt: &mut impl Runtime, signer: &Address, sets: u32) {
+    let Some(path) = usage_path(signer) else {
+        return;
+    };
+    let result = if sets == 0 {
+        Storage::remove(rt, &path)
+    } else {
+        Storage::insert(rt, &path, &sets)
+    };
+    if let Err(err) = result {
+        debug_msg!(rt, "Failed to write the chunks usage: {err:?}\n");
+    }
+}
+
+fn read_expiring(rt: &impl Runtime, level: u32) -> Vec<Blake2b> {
+    expiring_path(level)
+        .and_then(|path| Storage::get(rt, &path).ok().flatten())
+        .unwrap_or_default()
+}
+
+fn write_expiring(rt: &mut impl Runtime, level: u32, hashes: Vec<Blake2b>) {
+    let Some(path) = expiring_path(level) else {
+        return;
+    };
+    let result = if hashes.is_empty() {
+        Storage::remove(rt, &path)
+    } else {
+        Storage::insert(rt, &path, &hashes)
+    };
+    if let Err(err) = result {
+        debug_msg!(rt, "Failed to write the expiring chunks: {err:?}\n");
+    }
+}
+
+fn chunk_expiry(rt: &impl Runtime) -> u32 {
+    Storage::get(rt, &CHUNK_EXPIRY_PATH)
+        .ok()
+        .flatten()
+        .unwrap_or(DEFAULT_CHUNK_EXPIRY)
+}
+
+fn has_chunk(rt: &impl Runtime, hash: &Blake2b, index: u32) -> bool {
+    chunk_path(hash, index)
+        .map(|path| Storage::contains_key(rt, &path).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// Removes a chunk set along with its chunks
+fn discard(rt: &mut impl Runtime, hash: &Blake2b, pending: &PendingChunks) {
+    if let Some(path) = set_path(hash) {
+        let _ = rt.store_delete(&path);
+    }
+    let sets = read_usage(rt, &pending.signer);
+    write_usage(rt, &pending.signer, sets.saturating_sub(1));
+}
+
+/// Removes the chunk sets that are still incomplete at `level`
+pub fn remove_expired(rt: &mut impl Runtime, level: u32) {
+    // Chunk sets expiring at `level - 1` are the only ones left, as earlier
+    // levels were processed at the start of their successor
+    let Some(expired_at) = level.checked_sub(1) else {
+        return;
+    };
+    let expired = read_expiring(rt, expired_at);
+    if expired.is_empty() {
+        return;
+    }
+
+    for hash in expired {
+        if let Some(pending) = load(rt, &hash) {
+            debug_msg!(rt, "Discarding expired chunks: {}\n", hash.to_string());
+            discard(rt, &hash, &pending);
+        }
+    }
+    write_expiring(rt, expired_at, vec![]);
+}
+
+fn load(rt: &impl Runtime, hash: &Blake2b) -> Option<PendingChunks> {
+    Storage::get(rt, &pending_path(hash)?).ok()?
+}
+
+fn save(rt: &mut impl Runtime, hash: &Blake2b, pending: &PendingChunks) {
+    let Some(path) = pending_path(hash) else {
+        return;
+    };
+    if let Err(err) = Storage::insert(rt, &path, pending) {
+        debug_msg!(rt, "Failed to store chunks: {err:?}\n");
+    }
+}
+
+/// Reassembles the operation once all of its chunks have been received,
+/// otherwise records the progress of the chunk set
+fn complete(
+    rt: &mut impl Runtime,
+    hash: &Blake2b,
+    pending: PendingChunks,
+) -> Option<SignedOperation> {
+    let chunk_count = pending.header.chunk_count();
+    if pending.received < chunk_count {
+        save(rt, hash, &pending);
+        return None;
+    }
+
+    let chunks: Option<Vec<Vec<u8>>> = (0..chunk_count)
+        .map(|index| {
+            let path = chunk_path(hash, index)?;
+            Storage::get(rt, &path).ok().flatten()
+        })
+        .collect();
+
+    discard(rt, hash, &pending);
+    let mut expiring = read_expiring(rt, pending.expires_at);
+    expiring.retain(|expiring_hash| expiring_hash != hash);
+    write_expiring(rt, pending.expires_at, expiring);
+
+    let operation: SignedOperation = bincode::deserialize(&chunks?.concat()).ok()?;
+    if operation.public_key != pending.header.public_key
+        || operation.hash() != pending.header.operation_hash
+    {
+        debug_msg!(rt, "Reassembled operation does not match its header\n");
+        return None;
+    }
+    Some(operation)
+}
+
+/// Records a chunk header signed by the operation's signer, unless the signer
+/// has too many pending chunk sets
+pub fn read_header(rt: &mut impl Runtime, header: ChunkHeader, level: u32) {
+    if header.length == 0 || header.length > MAX_OPERATION_SIZE {
+        debug_msg!(rt, "Ignoring chunk header: invalid operation size\n");
+        return;
+    }
+    if header.chunk_hashes.len() != chunk_count(header.length) as usize {
+        debug_msg!(rt, "Ignoring chunk header: invalid chunk hashes\n");
+        return;
+    }
+    if header.verify().is_err() {
+        debug_msg!(rt, "Ignoring chunk header: invalid signature\n");
+        return;
+    }
+    let Ok(signer) = Address::try_from(&header.public_key) else {
+        return;
+    };
+
+    let hash = header.hash();
+    if load(rt, &hash).is_some() {
+        debug_msg!(rt, "Ignoring duplicate chunk header\n");
+        return;
+    }
+    let sets = read_usage(rt, &signer);
+    if sets >= MAX_PENDING_CHUNK_SETS {
+        debug_msg!(rt, "Ignoring chunk header: too many pending chunk sets\n");
+        return;
+    }
+    write_usage(rt, &signer, sets + 1);
+
+    let expires_at = level.saturating_add(chunk_expiry(rt));
+    let mut expiring = read_expiring(rt, expires_at);
+    expiring.push(hash.clone());
+    write_expiring(rt, expires_at, expiring);
+
+    let pending = PendingChunks {
+        header,
+        signer,
+        received: 0,
+        expires_at,
+    };
+    save(rt, &hash, &pending);
+}
+
+/// Records a chunk, returning the operation once its chunk set is complete.
+/// Chunks without a header, out of range, already received, or not matching
+/// their hash in the header are ignored.
+pub fn read_chunk(rt: &mut impl Runtime, chunk: Chunk) -> Option<SignedOperation> {
+    let Some(mut pending) = load(rt, &chunk.header_hash) else {
+        debug_msg!(rt, "Ignoring chunk {}: unknown chunk set\n", chunk.index);
+        return None;
+    };
+    let Some(chunk_hash) = pending.header.chunk_hashes.get(chunk.index as usize) else {
+        debug_msg!(rt, "Ignoring chunk {} out of range\n", chunk.index);
+        return None;
+    };
+    if &Blake2b::from(&chunk.data) != chunk_hash {
+        debug_msg!(rt, "Ignoring chunk {}: hash mismatch\n", chunk.index);
+        return None;
+    }
+    if has_chunk(rt, &chunk.header_hash, chunk.index) {
+        debug_msg!(rt, "Ignoring chunk {} already received\n", chunk.index);
+        return None;
+    }
+
+    let path = chunk_path(&chunk.header_hash, chunk.index)?;
+    if let Err(err) = Storage::insert(rt, &path, &chunk.data) {
+        debug_msg!(rt, "Failed to store chunk: {err:?}\n");
+        return None;
+    }
+
+    pending.received += 1;
+    complete(rt, &chunk.header_hash, pending)
+}
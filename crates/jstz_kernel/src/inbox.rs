@@ -1,8 +1,15 @@
-use jstz_crypto::public_key_hash::PublicKeyHash;
-use jstz_proto::operation::{external::Deposit, ExternalOperation, SignedOperation};
+use crate::chunks;
+use jstz_crypto::{hash::Blake2b, public_key_hash::PublicKeyHash};
+use jstz_proto::context::{block::Block, outbox::Outbox};
+use jstz_proto::operation::{
+    chunk,
+    external::{Deposit, FaDeposit, InboxId},
+    ExternalOperation, SignedOperation,
+};
 use num_traits::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::hash::ContractKt1Hash;
+use tezos_data_encoding::enc::BinWriter;
 use tezos_smart_rollup::inbox::ExternalMessageFrame;
 use tezos_smart_rollup::michelson::ticket::FA2_1Ticket;
 use tezos_smart_rollup::michelson::{
@@ -40,50 +47,103 @@ fn is_valid_native_deposit(
     match &creator.0 {
         Contract::Originated(kt1) if kt1 == native_ticketer => (),
         _ => {
-            debug_msg!(rt, "Deposit ignored because of different ticketer");
+            debug_msg!(rt, "Non-native deposit: different ticketer");
             return false;
         }
     };
 
     let native_ticket_id = MichelsonNat::from(NATIVE_TICKET_ID);
     if contents.0 != native_ticket_id {
-        debug_msg!(rt, "Deposit ignored because of different ticket id");
+        debug_msg!(rt, "Non-native deposit: different ticket id");
         return false;
     }
 
     if contents.1 != NATIVE_TICKET_CONTENT {
-        debug_msg!(rt, "Deposit ignored because of different ticket content");
+        debug_msg!(rt, "Non-native deposit: different ticket content");
         return false;
     }
 
     true
 }
 
+/// Hashes the ticketer and contents of a ticket, identifying it in the ticket table
+pub(crate) fn ticket_hash(ticket: &FA2_1Ticket) -> Option<Blake2b> {
+    let mut bytes = Vec::new();
+    ticket.creator().bin_write(&mut bytes).ok()?;
+    ticket.contents().bin_write(&mut bytes).ok()?;
+    Some(Blake2b::from(&bytes))
+}
+
 fn read_transfer(
     rt: &mut impl Runtime,
     transfer: Transfer<RollupType>,
     ticketer: &ContractKt1Hash,
+    inbox_id: InboxId,
 ) -> Option<Message> {
     debug_msg!(rt, "Internal message: transfer\n");
 
     let ticket = transfer.payload.1;
+    let amount = ticket.amount().to_u64()?;
+    let pkh = transfer.payload.0 .0.to_b58check();
+    let reciever = PublicKeyHash::from_base58(&pkh).ok()?;
 
     if is_valid_native_deposit(rt, &ticket, ticketer) {
-        let amount = ticket.amount().to_u64()?;
-        let pkh = transfer.payload.0 .0.to_b58check();
-        let reciever = PublicKeyHash::from_base58(&pkh).ok()?;
-        let content = Deposit { amount, reciever };
+        let content = Deposit {
+            inbox_id,
+            amount,
+            reciever,
+        };
         debug_msg!(rt, "Deposit: {content:?}\n");
         Some(Message::Internal(InternalMessage::Deposit(content)))
     } else {
-        None
+        let ticket_hash = ticket_hash(&ticket)?;
+        let content = FaDeposit {
+            inbox_id,
+            amount,
+            receiver: reciever,
+            ticket_hash,
+        };
+        debug_msg!(rt, "FA deposit: {content:?}\n");
+        Some(Message::Internal(InternalMessage::FaDeposit(content)))
     }
 }
 
-fn read_external_message(rt: &mut impl Runtime, bytes: &[u8]) -> Option<ExternalMessage> {
-    let msg: ExternalMessage = bincode::deserialize(bytes).ok()?;
-    debug_msg!(rt, "External message: {msg:?}\n");
-    Some(msg)
+fn read_external_message(
+    rt: &mut impl Runtime,
+    bytes: &[u8],
+    level: u32,
+) -> Option<ExternalMessage> {
+    let msg = match bytes {
+        [chunk::EXTERNAL_MESSAGE_TAG, chunk::EXTERNAL_MESSAGE_VERSION, msg @ ..] => {
+            bincode::deserialize(msg).ok()?
+        }
+        [chunk::EXTERNAL_MESSAGE_TAG, version, ..] => {
+            debug_msg!(rt, "Unsupported external message version {version}\n");
+            return None;
+        }
+        // Unversioned messages carry a single operation
+        _ => chunk::ExternalMessage::Operation(bincode::deserialize(bytes).ok()?),
+    };
+    match msg {
+        chunk::ExternalMessage::Operation(msg) => {
+            debug_msg!(rt, "External message: {msg:?}\n");
+            Some(msg)
+        }
+        chunk::ExternalMessage::ChunkHeader(header) => {
+            debug_msg!(rt, "External message: chunk header {header:?}\n");
+            chunks::read_header(rt, header, level);
+            None
+        }
+        chunk::ExternalMessage::Chunk(chunk) => {
+            debug_msg!(
+                rt,
+                "External message: chunk {} of {}\n",
+                chunk.index,
+                chunk.header_hash.to_string()
+            );
+            chunks::read_chunk(rt, chunk)
+        }
+    }
 }
 
 pub fn read_message(rt: &mut impl Runtime, ticketer: ContractKt1Hash) -> Option<Message> {
@@ -97,6 +157,13 @@ pub fn read_message(rt: &mut impl Runtime, ticketer: ContractKt1Hash) -> Option<
             // Start of level message pushed by the Layer 1 at the
             // beginning of eavh level.
             debug_msg!(rt, "Internal message: start of level\n");
+            if let Err(err) = Block::set_level(rt, input.level) {
+                debug_msg!(rt, "Failed to record the level: {err:?}\n");
+            }
+            if let Err(err) = Outbox::start_level(rt, input.level) {
+                debug_msg!(rt, "Failed to reset the outbox counter: {err:?}\n");
+            }
+            chunks::remove_expired(rt, input.level);
             None
         }
         InboxMessage::Internal(InternalInboxMessage::InfoPerLevel(info)) => {
@@ -109,6 +176,13 @@ pub fn read_message(rt: &mut impl Runtime, ticketer: ContractKt1Hash) -> Option<
                 info.predecessor,
                 info.predecessor_timestamp
             );
+            if let Err(err) = Block::set_predecessor(
+                rt,
+                info.predecessor_timestamp.i64(),
+                &info.predecessor,
+            ) {
+                debug_msg!(rt, "Failed to record the predecessor block: {err:?}\n");
+            }
             None
         }
         InboxMessage::Internal(InternalInboxMessage::EndOfLevel) => {
@@ -127,7 +201,11 @@ pub fn read_message(rt: &mut impl Runtime, ticketer: ContractKt1Hash) -> Option<
                 );
                 return None;
             };
-            read_transfer(rt, transfer, &ticketer)
+            let inbox_id = InboxId {
+                l1_level: input.level,
+                l1_message_id: input.id,
+            };
+            read_transfer(rt, transfer, &ticketer, inbox_id)
         }
         InboxMessage::External(bytes) => match ExternalMessageFrame::parse(bytes) {
             Ok(frame) => match frame {
@@ -143,10 +221,13 @@ pub fn read_message(rt: &mut impl Runtime, ticketer: ContractKt1Hash) -> Option<
                         );
                         None
                     } else {
-                        match read_external_message(rt, contents) {
+                        match read_external_message(rt, contents, input.level) {
                             Some(msg) => Some(Message::External(msg)),
                             None => {
-                                debug_msg!(rt, "Failed to parse the external message\n");
+                                debug_msg!(
+                                    rt,
+                                    "No operation read from the external message\n"
+                                );
                                 None
                             }
                         }
@@ -163,10 +244,20 @@ pub fn read_message(rt: &mut impl Runtime, ticketer: ContractKt1Hash) -> Option<
 
 #[cfg(test)]
 mod test {
+    use jstz_crypto::keypair_from_passphrase;
     use jstz_mock::mock::{JstzMockHost, MockNativeDeposit};
-    use jstz_proto::operation::external;
+    use jstz_proto::{
+        context::account::{Address, Nonce},
+        operation::{
+            chunk::{
+                Chunk, ExternalMessage, EXTERNAL_MESSAGE_TAG, EXTERNAL_MESSAGE_VERSION,
+                MAX_CHUNK_COUNT, MAX_OPERATION_SIZE,
+            },
+            external, Content, Operation, RunFunction, SignedOperation,
+        },
+    };
     use tezos_crypto_rs::hash::{ContractKt1Hash, HashTrait};
-    use tezos_smart_rollup::types::SmartRollupAddress;
+    use tezos_smart_rollup::{prelude::Runtime, types::SmartRollupAddress};
 
     use super::{read_message, InternalMessage, Message};
 
@@ -195,6 +286,7 @@ mod test {
         if let Message::Internal(InternalMessage::Deposit(external::Deposit {
             amount,
             reciever,
+            ..
         })) =
             read_message(host.rt(), ticketer).expect("Expected message but non received")
         {
@@ -205,10 +297,20 @@ mod test {
         }
     }
 
-    #[test]
-    fn read_message_native_deposit_ignored_different_ticketer() {
+    fn read_fa_deposit(deposit: &MockNativeDeposit) -> external::FaDeposit {
         let mut host = JstzMockHost::new(true);
         let ticketer = host.get_ticketer();
+        host.add_deposit_message(deposit);
+        match read_message(host.rt(), ticketer)
+            .expect("Expected message but non received")
+        {
+            Message::Internal(InternalMessage::FaDeposit(fa_deposit)) => fa_deposit,
+            _ => panic!("Expected FA deposit message"),
+        }
+    }
+
+    #[test]
+    fn read_message_fa_deposit_different_ticketer() {
         let deposit = MockNativeDeposit {
             ticketer: ContractKt1Hash::from_b58check(
                 "KT1KRj5VMNmhxobTJBPq7u2kacqbxu9Cntx6",
@@ -216,31 +318,177 @@ mod test {
             .unwrap(),
             ..MockNativeDeposit::default()
         };
-        host.add_deposit_message(&deposit);
-        assert_eq!(read_message(host.rt(), ticketer), None);
+        let fa_deposit = read_fa_deposit(&deposit);
+        assert_eq!(fa_deposit.amount, 100);
+        assert_eq!(
+            fa_deposit.receiver.to_base58(),
+            deposit.receiver.to_b58check()
+        );
     }
 
     #[test]
-    fn read_message_native_deposit_ignored_different_ticket_id() {
-        let mut host = JstzMockHost::new(true);
-        let ticketer = host.get_ticketer();
+    fn read_message_fa_deposit_different_ticket_id() {
         let deposit = MockNativeDeposit {
             ticket_content: (1, None),
             ..MockNativeDeposit::default()
         };
-        host.add_deposit_message(&deposit);
-        assert_eq!(read_message(host.rt(), ticketer), None);
+        let fa_deposit = read_fa_deposit(&deposit);
+        assert_eq!(fa_deposit.amount, 100);
     }
 
     #[test]
-    fn read_message_native_deposit_ignored_different_ticket_value() {
-        let mut host = JstzMockHost::new(true);
-        let ticketer = host.get_ticketer();
-        let deposit = MockNativeDeposit {
+    fn read_message_fa_deposit_ticket_hash_depends_on_content() {
+        let first = read_fa_deposit(&MockNativeDeposit {
             ticket_content: (0, Some(b"1234".to_vec())),
             ..MockNativeDeposit::default()
+        });
+        let second = read_fa_deposit(&MockNativeDeposit {
+            ticket_content: (0, Some(b"5678".to_vec())),
+            ..MockNativeDeposit::default()
+        });
+        assert_ne!(first.ticket_hash, second.ticket_hash);
+    }
+
+    fn encode(message: &ExternalMessage) -> Vec<u8> {
+        let mut bytes = vec![EXTERNAL_MESSAGE_TAG, EXTERNAL_MESSAGE_VERSION];
+        bytes.extend(bincode::serialize(message).unwrap());
+        bytes
+    }
+
+    fn signed_operation(host: &mut JstzMockHost, body_size: usize) -> SignedOperation {
+        let (sk, pk) = keypair_from_passphrase("caller").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: format!("tezos://{}/", source).parse().unwrap(),
+                method: http::Method::POST,
+                headers: http::HeaderMap::new(),
+                body: Some(vec![0; body_size]),
+                gas_limit: 100_000,
+            }),
         };
-        host.add_deposit_message(&deposit);
-        assert_eq!(read_message(host.rt(), ticketer), None);
+        let signature = sk.sign(operation.hash()).unwrap();
+        SignedOperation::new(pk, signature, operation)
+    }
+
+    #[test]
+    fn read_message_accepts_unversioned_operation() {
+        let mut host = JstzMockHost::new(true);
+        let signed_operation = signed_operation(&mut host, 10);
+        host.add_external_message(bincode::serialize(&signed_operation).unwrap());
+
+        let ticketer = host.get_ticketer();
+        assert_eq!(
+            read_message(host.rt(), ticketer),
+            Some(Message::External(signed_operation))
+        );
+    }
+
+    #[test]
+    fn read_message_reassembles_chunked_operation() {
+        let mut host = JstzMockHost::new(true);
+        let signed_operation = signed_operation(&mut host, 8_000);
+
+        let encoded_operation = bincode::serialize(&signed_operation).unwrap();
+        let messages = ExternalMessage::split(&signed_operation, &encoded_operation);
+        let count = messages.len();
+        for message in messages {
+            host.add_external_message(encode(&message));
+        }
+
+        let ticketer = host.get_ticketer();
+        for _ in 1..count {
+            assert_eq!(read_message(host.rt(), ticketer.clone()), None);
+        }
+        assert_eq!(
+            read_message(host.rt(), ticketer),
+            Some(Message::External(signed_operation))
+        );
+    }
+
+    #[test]
+    fn read_message_ignores_forged_and_out_of_range_chunks() {
+        let mut host = JstzMockHost::new(true);
+        let signed_operation = signed_operation(&mut host, 8_000);
+
+        let encoded_operation = bincode::serialize(&signed_operation).unwrap();
+        let mut messages = ExternalMessage::split(&signed_operation, &encoded_operation);
+        let last = messages.pop().unwrap();
+        let header_hash = match &last {
+            ExternalMessage::Chunk(chunk) => chunk.header_hash.clone(),
+            _ => panic!("Expected chunk"),
+        };
+        let forged = |index| {
+            ExternalMessage::Chunk(Chunk {
+                header_hash: header_hash.clone(),
+                index,
+                data: vec![1; 10],
+            })
+        };
+        // Chunks received before their header are ignored
+        messages.insert(0, forged(0));
+        // Chunks not matching their hash in the header don't take the place
+        // of the genuine chunks
+        messages.insert(2, forged(0));
+        messages.insert(3, forged(1));
+        messages.push(forged(11));
+        messages.push(last);
+
+        let count = messages.len();
+        for message in messages {
+            host.add_external_message(encode(&message));
+        }
+
+        let ticketer = host.get_ticketer();
+        for _ in 1..count {
+            assert_eq!(read_message(host.rt(), ticketer.clone()), None);
+        }
+        assert_eq!(
+            read_message(host.rt(), ticketer),
+            Some(Message::External(signed_operation))
+        );
+    }
+
+    #[test]
+    fn read_message_ignores_chunk_header_without_valid_signature() {
+        let mut host = JstzMockHost::new(true);
+        let signed_operation = signed_operation(&mut host, 8_000);
+        let (_, other_pk) = keypair_from_passphrase("other").unwrap();
+
+        let encoded_operation = bincode::serialize(&signed_operation).unwrap();
+        let mut messages = ExternalMessage::split(&signed_operation, &encoded_operation);
+        let ExternalMessage::ChunkHeader(header) = &mut messages[0] else {
+            panic!("Expected chunk header")
+        };
+        header.public_key = other_pk;
+        let header_hash = header.hash();
+        for message in messages.iter_mut().skip(1) {
+            if let ExternalMessage::Chunk(chunk) = message {
+                chunk.header_hash = header_hash.clone();
+            }
+        }
+
+        for message in &messages {
+            host.add_external_message(encode(message));
+        }
+        let ticketer = host.get_ticketer();
+        for _ in 0..messages.len() {
+            assert_eq!(read_message(host.rt(), ticketer.clone()), None);
+        }
+    }
+
+    #[test]
+    fn chunk_header_fits_in_inbox_message() {
+        let mut host = JstzMockHost::new(true);
+        let signed_operation = signed_operation(&mut host, 10);
+        let encoded_operation = vec![0; MAX_OPERATION_SIZE as usize];
+        let messages = ExternalMessage::split(&signed_operation, &encoded_operation);
+        assert_eq!(messages.len(), MAX_CHUNK_COUNT as usize + 1);
+        assert!(encode(&messages[0]).len() < 4096 - 32);
     }
 }
@@ -9,6 +9,7 @@ use tezos_smart_rollup::{
 
 use crate::inbox::{read_message, Message};
 
+pub mod chunks;
 pub mod inbox;
 
 pub const TICKETER: RefPath = RefPath::assert_from(b"/ticketer");
@@ -23,7 +24,10 @@ fn handle_message(hrt: &mut impl Runtime, message: Message) -> Result<()> {
 
     match message {
         Message::Internal(external_operation) => {
-            executor::execute_external_operation(hrt, &mut tx, external_operation)?
+            let receipt =
+                executor::execute_external_operation(hrt, &mut tx, external_operation);
+            debug_msg!(hrt, "Receipt: {receipt:?}\n");
+            receipt.write(hrt, &mut tx)?
         }
         Message::External(signed_operation) => {
             debug_msg!(hrt, "External operation: {signed_operation:?}\n");
@@ -51,12 +55,36 @@ pub fn entry(rt: &mut impl Runtime) {
 #[cfg(test)]
 mod test {
 
-    use jstz_core::kv::Transaction;
+    use jstz_core::kv::{Storage, Transaction};
+    use jstz_crypto::keypair_from_passphrase;
     use jstz_mock::mock::{JstzMockHost, MockNativeDeposit};
-    use jstz_proto::context::account::Account;
-    use tezos_smart_rollup::types::{Contract, PublicKeyHash};
+    use jstz_proto::{
+        context::{
+            account::{
+                salted_address, Account, Address, FunctionMetadata, Nonce, ParsedCode,
+            },
+            block::Block,
+            ticket_table::TicketTable,
+        },
+        operation::{
+            BatchItem, Content, DeployFunction, Operation, RevealDeployFunction,
+            RunFunction, SignedOperation, Transfer, UpgradeFunction, Withdraw,
+        },
+        preimage::{self, PREIMAGE_HASH_SIZE},
+        receipt::{self, Content as ReceiptContent, Receipt},
+    };
+    use tezos_crypto_rs::hash::SmartRollupHash;
+    use tezos_smart_rollup::{
+        prelude::Runtime,
+        storage::path::OwnedPath,
+        types::{Contract, PublicKeyHash},
+    };
 
-    use crate::{entry, read_ticketer};
+    use crate::{
+        entry, handle_message,
+        inbox::{read_message, ticket_hash, Message},
+        read_ticketer,
+    };
 
     #[test]
     fn read_ticketer_succeeds() {
@@ -87,4 +115,1053 @@ mod test {
             _ => panic!("Unexpected receiver"),
         }
     }
+
+    #[test]
+    fn native_deposit_writes_receipt() {
+        let mut host = JstzMockHost::new(true);
+        let deposit = MockNativeDeposit::default();
+        host.add_deposit_message(&deposit);
+        let ticketer = host.get_ticketer();
+        let message = read_message(host.rt(), ticketer).expect("Expected message");
+        let hash = match &message {
+            Message::Internal(external_operation) => external_operation.hash(),
+            _ => panic!("Expected deposit message"),
+        };
+
+        handle_message(host.rt(), message).unwrap();
+
+        let receipt_path =
+            OwnedPath::try_from(format!("/jstz_receipt/{}", hash.to_string())).unwrap();
+        let receipt = Storage::get::<Receipt>(host.rt(), &receipt_path)
+            .unwrap()
+            .expect("Expected receipt");
+        match receipt.inner {
+            Ok(ReceiptContent::Deposit(receipt::Deposit { receiver, amount })) => {
+                assert_eq!(receiver.to_base58(), deposit.receiver.to_b58check());
+                assert_eq!(amount, 100);
+            }
+            _ => panic!("Expected deposit receipt"),
+        }
+    }
+
+    #[test]
+    fn fa_deposit_credits_ticket_table() {
+        let mut host = JstzMockHost::default();
+        let deposit = MockNativeDeposit {
+            ticket_content: (1, Some(b"fa".to_vec())),
+            ..MockNativeDeposit::default()
+        };
+        host.add_deposit_message(&deposit);
+        host.rt().run_level(entry);
+
+        let ticket_hash = ticket_hash(&deposit.to_jstz().1).unwrap();
+        let receiver = Address::from_base58(&deposit.receiver.to_b58check()).unwrap();
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        let balance =
+            TicketTable::get_balance(host.rt(), tx, &receiver, &ticket_hash).unwrap();
+        assert_eq!(balance, 100);
+        let native_balance = Account::balance(host.rt(), tx, &receiver).unwrap();
+        assert_eq!(native_balance, 0);
+    }
+
+    #[test]
+    fn withdraw_debits_source_account() {
+        let mut host = JstzMockHost::default();
+        let (sk, pk) = keypair_from_passphrase("withdrawer").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(host.rt(), tx, &source, 100).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::Withdraw(Withdraw {
+                amount: 40,
+                receiver: source.clone(),
+            }),
+        };
+        let signature = sk.sign(operation.hash()).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        tx.begin();
+        let amount = Account::balance(host.rt(), tx, &source).unwrap();
+        assert_eq!(amount, 60);
+    }
+
+    #[test]
+    fn transfer_moves_balance_between_accounts() {
+        let mut host = JstzMockHost::default();
+        let (sk, pk) = keypair_from_passphrase("sender").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+        let (_, receiver_pk) = keypair_from_passphrase("receiver").unwrap();
+        let receiver = Address::try_from(&receiver_pk).unwrap();
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(host.rt(), tx, &source, 100).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::Transfer(Transfer {
+                amount: 40,
+                receiver: receiver.clone(),
+            }),
+        };
+        let signature = sk.sign(operation.hash()).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        tx.begin();
+        assert_eq!(Account::balance(host.rt(), tx, &source).unwrap(), 60);
+        assert_eq!(Account::balance(host.rt(), tx, &receiver).unwrap(), 40);
+    }
+
+    #[test]
+    fn failing_batch_is_rolled_back() {
+        let mut host = JstzMockHost::default();
+        let (sk, pk) = keypair_from_passphrase("withdrawer").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(host.rt(), tx, &source, 100).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let withdraw = |amount| {
+            BatchItem::Withdraw(Withdraw {
+                amount,
+                receiver: source.clone(),
+            })
+        };
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::Batch(vec![withdraw(40), withdraw(80)]),
+        };
+        let signature = sk.sign(operation.hash()).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        tx.begin();
+        let amount = Account::balance(host.rt(), tx, &source).unwrap();
+        assert_eq!(amount, 100);
+    }
+
+    #[test]
+    fn batch_reports_failing_item() {
+        let mut host = JstzMockHost::default();
+        let deploy = || {
+            BatchItem::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response();".to_string(),
+                )
+                .unwrap(),
+                account_credit: 0,
+                salt: None,
+                metadata: None,
+                gas_limit: None,
+                owner: None,
+            })
+        };
+
+        let receipt =
+            apply_operation(&mut host, Content::Batch(vec![deploy(), deploy()]));
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            receipt::ReceiptError::BatchItemFailed {
+                index: 1,
+                error: Box::new(receipt::ReceiptError::AlreadyDeployed),
+            }
+        );
+    }
+
+    #[test]
+    fn operation_for_another_rollup_is_rejected() {
+        let mut host = JstzMockHost::default();
+        let (sk, pk) = keypair_from_passphrase("withdrawer").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+
+        let operation = Operation {
+            rollup_address: SmartRollupHash::from_base58_check(
+                "sr1Ghq66tYK9y3r8CC1Tf8i8m5nxh8nTvZEf",
+            )
+            .unwrap(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::Withdraw(Withdraw {
+                amount: 0,
+                receiver: source.clone(),
+            }),
+        };
+        let hash = operation.hash();
+        let signature = sk.sign(&hash).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        let receipt_path =
+            OwnedPath::try_from(format!("/jstz_receipt/{}", hash.to_string())).unwrap();
+        let receipt = Storage::get::<Receipt>(host.rt(), &receipt_path)
+            .unwrap()
+            .expect("Expected receipt");
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            receipt::ReceiptError::InvalidRollupAddress
+        );
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        let nonce = Account::nonce(host.rt(), tx, &source).unwrap();
+        assert_eq!(*nonce, Nonce::default());
+    }
+
+    #[test]
+    fn uncaught_exception_is_recorded_in_receipt() {
+        let mut host = JstzMockHost::default();
+        let (sk, pk) = keypair_from_passphrase("caller").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+
+        let code = ParsedCode::try_from(
+            "export default () => { console.log(\"before\"); throw new TypeError(\"boom\"); }"
+                .to_string(),
+        )
+        .unwrap();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::create(host.rt(), tx, &function, 0, Some(code), None).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: format!("tezos://{}/", function).parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 100_000,
+            }),
+        };
+        let hash = operation.hash();
+        let signature = sk.sign(&hash).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        let receipt_path =
+            OwnedPath::try_from(format!("/jstz_receipt/{}", hash.to_string())).unwrap();
+        let receipt = Storage::get::<Receipt>(host.rt(), &receipt_path)
+            .unwrap()
+            .expect("Expected receipt");
+        let receipt::ReceiptError::RunFunctionFailed {
+            error,
+            gas_used,
+            logs,
+        } = receipt.inner.unwrap_err()
+        else {
+            panic!("Expected a failed run")
+        };
+        match *error {
+            receipt::ReceiptError::JsException { name, message, .. } => {
+                assert_eq!(name, "TypeError");
+                assert_eq!(message, "boom");
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+        assert!(gas_used > 0);
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].text, "before");
+    }
+
+    /// Runs the smart function at `address`, returning the error its
+    /// failure is recorded with
+    fn failed_run(host: &mut JstzMockHost, address: &Address) -> receipt::ReceiptError {
+        let receipt = apply_operation(
+            host,
+            Content::RunFunction(RunFunction {
+                uri: format!("tezos://{}/", address).parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 100_000,
+            }),
+        );
+        match receipt.inner {
+            Err(receipt::ReceiptError::RunFunctionFailed { error, .. }) => *error,
+            inner => panic!("Expected a failed run, got {inner:?}"),
+        }
+    }
+
+    #[test]
+    fn host_errors_are_recorded_in_receipt() {
+        let mut host = JstzMockHost::default();
+        let (_, pk) = keypair_from_passphrase("nobody").unwrap();
+        let nobody = Address::try_from(&pk).unwrap();
+
+        let uncaught = r#"export default () => {
+            Ledger.transfer("tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx", 10);
+            return new Response();
+        }"#;
+        let rethrown = r#"export default () => {
+            try {
+                Ledger.transfer("tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx", 10);
+            } catch (e) {
+                throw new RangeError("no funds");
+            }
+            return new Response();
+        }"#;
+        let tx = &mut Transaction::default();
+        tx.begin();
+        let mut functions = vec![];
+        for (passphrase, code) in [("uncaught", uncaught), ("rethrown", rethrown)] {
+            let (_, pk) = keypair_from_passphrase(passphrase).unwrap();
+            let address = Address::try_from(&pk).unwrap();
+            let code = ParsedCode::try_from(code.to_string()).unwrap();
+            Account::create(host.rt(), tx, &address, 0, Some(code), None).unwrap();
+            functions.push(address);
+        }
+        tx.commit(host.rt()).unwrap();
+
+        assert!(matches!(
+            failed_run(&mut host, &nobody),
+            receipt::ReceiptError::InvalidAddress
+        ));
+        assert!(matches!(
+            failed_run(&mut host, &functions[0]),
+            receipt::ReceiptError::InsufficientFunds {
+                balance: 0,
+                amount: 10
+            }
+        ));
+        match failed_run(&mut host, &functions[1]) {
+            receipt::ReceiptError::JsException { name, message } => {
+                assert_eq!(name, "RangeError");
+                assert_eq!(message, "no funds");
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn revealing_preimages_is_charged_gas() {
+        let mut host = JstzMockHost::default();
+        let code = format!(
+            "export default () => new Response({:?});",
+            "a".repeat(10_000)
+        );
+        let mut pages = Vec::new();
+        let root_hash =
+            preimage::prepare(code.as_bytes(), |_, page| pages.push(page)).unwrap();
+        for page in pages {
+            host.rt().set_preimage(page);
+        }
+        let root_hash: &[u8; PREIMAGE_HASH_SIZE] = root_hash.as_ref();
+
+        let receipt = apply_operation(
+            &mut host,
+            Content::RevealDeployFunction(RevealDeployFunction {
+                root_hash: root_hash.to_vec(),
+                account_credit: 0,
+                salt: None,
+                metadata: None,
+                gas_limit: Some(preimage::REVEAL_GAS_PER_PAGE),
+                owner: None,
+            }),
+        );
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::GasLimitExceeded)
+        ));
+    }
+
+    #[test]
+    fn large_function_is_deployed_from_preimages() {
+        let mut host = JstzMockHost::default();
+        let (sk, pk) = keypair_from_passphrase("deployer").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+
+        let code = format!(
+            "export default () => new Response({:?});",
+            "a".repeat(10_000)
+        );
+        let mut pages = Vec::new();
+        let root_hash =
+            preimage::prepare(code.as_bytes(), |_, page| pages.push(page)).unwrap();
+        for page in pages {
+            host.rt().set_preimage(page);
+        }
+        let root_hash: &[u8; PREIMAGE_HASH_SIZE] = root_hash.as_ref();
+
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source: source.clone(),
+            nonce: Nonce::default(),
+            content: Content::RevealDeployFunction(RevealDeployFunction {
+                root_hash: root_hash.to_vec(),
+                account_credit: 0,
+                salt: Some(b"salt".to_vec()),
+                metadata: None,
+                gas_limit: Some(100_000),
+                owner: None,
+            }),
+        };
+        let hash = operation.hash();
+        let signature = sk.sign(&hash).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        let receipt_path =
+            OwnedPath::try_from(format!("/jstz_receipt/{}", hash.to_string())).unwrap();
+        let receipt = Storage::get::<Receipt>(host.rt(), &receipt_path)
+            .unwrap()
+            .expect("Expected receipt");
+        let address = match receipt.inner {
+            Ok(ReceiptContent::DeployFunction(deploy)) => deploy.address,
+            _ => panic!("Expected deploy function receipt"),
+        };
+        let code_hash = ParsedCode::try_from(code.clone()).unwrap().hash();
+        let expected = salted_address(&source, b"salt", &code_hash).unwrap();
+        assert_eq!(address, expected);
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        let function_code = Account::function_code(host.rt(), tx, &address)
+            .unwrap()
+            .expect("Expected function code");
+        assert_eq!(function_code.to_string(), code);
+    }
+
+    /// Signs `content` by the "caller" account and applies it, returning the
+    /// receipt of the operation
+    fn apply_operation(host: &mut JstzMockHost, content: Content) -> Receipt {
+        let (sk, pk) = keypair_from_passphrase("caller").unwrap();
+        let source = Address::try_from(&pk).unwrap();
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        let nonce = *Account::nonce(host.rt(), tx, &source).unwrap();
+
+        let operation = Operation {
+            rollup_address: host.rt().reveal_metadata().address().hash().clone(),
+            expiry_level: None,
+            source,
+            nonce,
+            content,
+        };
+        let hash = operation.hash();
+        let signature = sk.sign(&hash).unwrap();
+        let signed_operation = SignedOperation::new(pk, signature, operation);
+
+        handle_message(host.rt(), Message::External(signed_operation)).unwrap();
+
+        let receipt_path =
+            OwnedPath::try_from(format!("/jstz_receipt/{}", hash.to_string())).unwrap();
+        Storage::get::<Receipt>(host.rt(), &receipt_path)
+            .unwrap()
+            .expect("Expected receipt")
+    }
+
+    /// Calls the smart function at `address` with a GET request, returning the
+    /// body of the response
+    fn run_function(
+        host: &mut JstzMockHost,
+        address: &Address,
+        gas_limit: usize,
+    ) -> String {
+        let receipt = apply_operation(
+            host,
+            Content::RunFunction(RunFunction {
+                uri: format!("tezos://{}/", address).parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit,
+            }),
+        );
+        match receipt.inner {
+            Ok(ReceiptContent::RunFunction(run)) => {
+                String::from_utf8(run.body.expect("Expected body")).unwrap()
+            }
+            inner => panic!("Expected run function receipt, got {inner:?}"),
+        }
+    }
+
+    /// Deploys `code` and calls it with a GET request, returning the body of
+    /// the response
+    fn call_function(host: &mut JstzMockHost, code: &str, gas_limit: usize) -> String {
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+
+        let code = ParsedCode::try_from(code.to_string()).unwrap();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::create(host.rt(), tx, &function, 0, Some(code), None).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        run_function(host, &function, gas_limit)
+    }
+
+    /// Deploys `code` from the "caller" account, running its `init` export
+    /// within `gas_limit`, and returns the receipt of the deployment
+    fn deploy_function(
+        host: &mut JstzMockHost,
+        code: &str,
+        gas_limit: Option<usize>,
+    ) -> Receipt {
+        apply_operation(
+            host,
+            Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(code.to_string()).unwrap(),
+                account_credit: 0,
+                salt: None,
+                metadata: None,
+                gas_limit,
+                owner: None,
+            }),
+        )
+    }
+
+    #[test]
+    fn smart_function_observes_l1_block() {
+        let mut host = JstzMockHost::default();
+        host.rt().run_level(entry);
+
+        let level = Block::level(host.rt()).unwrap();
+        let timestamp = Block::timestamp(host.rt()).unwrap();
+        let predecessor = Block::predecessor(host.rt())
+            .unwrap()
+            .expect("Expected predecessor block");
+
+        let body = call_function(
+            &mut host,
+            "export default () => new Response(\
+                `${Date.now()} ${Jstz.block.timestamp} ${Jstz.block.level} ${Jstz.block.predecessor}`);",
+            100_000,
+        );
+        assert_eq!(
+            body,
+            format!(
+                "{} {timestamp} {level} {}",
+                timestamp * 1000,
+                predecessor.to_base58_check()
+            )
+        );
+    }
+
+    #[test]
+    fn kv_lists_keys_by_prefix() {
+        let mut host = JstzMockHost::default();
+        let body = call_function(
+            &mut host,
+            r#"export default () => {
+                Kv.set("users/bob", 1);
+                Kv.set("users/alice", 2);
+                Kv.set("posts/1", 3);
+                Kv.delete("users/bob");
+                Kv.set("users/carol", 4);
+                const page = Kv.entries("users/", { limit: 1, cursor: "users/alice" });
+                return new Response(JSON.stringify([Kv.keys("users/"), page]));
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(
+            body,
+            r#"[["users/alice","users/carol"],[["users/carol",4]]]"#
+        );
+    }
+
+    #[test]
+    fn kv_lists_keys_across_index_pages() {
+        let mut host = JstzMockHost::default();
+        let body = call_function(
+            &mut host,
+            r#"export default () => {
+                const key = (i) => `k${String(i).padStart(3, "0")}`;
+                for (let i = 199; i >= 0; i--) Kv.set(key(i), i);
+                for (let i = 0; i < 100; i += 2) Kv.delete(key(i));
+                const keys = Kv.keys("k");
+                const page = Kv.keys("k", { limit: 2, cursor: "k150" });
+                return new Response(JSON.stringify(
+                    [keys.length, keys[0], keys[keys.length - 1], page, Kv.hasUnlistedKeys()]
+                ));
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(body, r#"[150,"k001","k199",["k151","k152"],false]"#);
+    }
+
+    #[test]
+    fn kv_listing_fails_until_keys_stored_before_listing_are_marked() {
+        let mut host = JstzMockHost::default();
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+
+        let legacy_path =
+            OwnedPath::try_from(format!("/jstz_kv/{}/legacy", function)).unwrap();
+        Storage::insert(host.rt(), &legacy_path, &"1".to_string()).unwrap();
+
+        let body = call_function(
+            &mut host,
+            r#"export default () => {
+                Kv.set("new", 1);
+                const unlisted = Kv.hasUnlistedKeys();
+                const fails = (list) => {
+                    try { list(); return false; } catch (e) { return true; }
+                };
+                const failed = [fails(() => Kv.keys()), fails(() => Kv.entries())];
+                Kv.set("legacy", 2);
+                Kv.markListed();
+                return new Response(
+                    JSON.stringify([unlisted, failed, Kv.keys(), Kv.hasUnlistedKeys()])
+                );
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(body, r#"[true,[true,true],["legacy","new"],false]"#);
+    }
+
+    #[test]
+    fn kv_preserves_value_types() {
+        let mut host = JstzMockHost::default();
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+
+        // Values written before strings and bytes were supported are JSON text
+        let legacy_path =
+            OwnedPath::try_from(format!("/jstz_kv/{}/legacy", function)).unwrap();
+        Storage::insert(host.rt(), &legacy_path, &"{\"a\":1}".to_string()).unwrap();
+
+        let body = call_function(
+            &mut host,
+            r#"export default () => {
+                Kv.set("string", "hello");
+                Kv.set("bytes", new Uint8Array([0, 1, 255]).subarray(1));
+                Kv.set("view", new DataView(new Uint8Array([0, 1, 2, 3]).buffer, 1, 2));
+                const bytes = Kv.get("bytes");
+                return new Response(JSON.stringify([
+                    Kv.get("string"),
+                    bytes instanceof Uint8Array,
+                    Array.from(bytes),
+                    Array.from(Kv.get("view")),
+                    Kv.get("legacy"),
+                ]));
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(body, r#"["hello",true,[1,255],[1,2],{"a":1}]"#);
+    }
+
+    #[test]
+    fn kv_transaction_rolls_back_on_error() {
+        let mut host = JstzMockHost::default();
+        let body = call_function(
+            &mut host,
+            r#"export default () => {
+                Kv.set("a", 1);
+                try {
+                    Kv.transaction(() => {
+                        Kv.set("a", 2);
+                        throw new Error("sync");
+                    });
+                } catch (e) {}
+                Kv.transaction(() => Kv.set("b", 3));
+                return new Response(JSON.stringify([Kv.get("a"), Kv.get("b")]));
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(body, "[1,3]");
+    }
+
+    #[test]
+    fn kv_transaction_rejects_async_callbacks() {
+        let mut host = JstzMockHost::default();
+        let body = call_function(
+            &mut host,
+            r#"export default async () => {
+                const errors = [];
+                try {
+                    Kv.transaction(async () => {
+                        Kv.set("a", 1);
+                        await null;
+                        Kv.set("b", 2);
+                        throw new Error("async");
+                    });
+                } catch (e) {
+                    errors.push(e.name);
+                }
+                try {
+                    Kv.transaction(() => {
+                        Kv.set("c", 3);
+                        return Promise.resolve();
+                    });
+                } catch (e) {
+                    errors.push(e.name);
+                }
+                await null;
+                return new Response(JSON.stringify(
+                    [errors, Kv.get("a"), Kv.get("b"), Kv.get("c")]
+                ));
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(body, r#"[["TypeError","TypeError"],null,null,null]"#);
+    }
+
+    #[test]
+    fn nested_calls_evaluate_module_again() {
+        let mut host = JstzMockHost::default();
+        let body = call_function(
+            &mut host,
+            r#"let calls = 0;
+            export default async (request) => {
+                calls++;
+                if (new URL(request.url).pathname === "/nested") {
+                    return new Response(String(calls));
+                }
+                const nested = await SmartFunction.call(
+                    new Request(`tezos://${Ledger.selfAddress}/nested`),
+                );
+                const body = await nested.text();
+                return new Response(`${calls},${body}`);
+            }"#,
+            1_000_000,
+        );
+        assert_eq!(body, "1,1");
+    }
+
+    #[test]
+    fn nested_calls_are_charged_parsing_once_per_operation() {
+        let mut host = JstzMockHost::default();
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+
+        let code = format!(
+            r#"export default async (request) => {{
+                if (new URL(request.url).pathname === "/nested") {{
+                    return new Response();
+                }}
+                await SmartFunction.call(
+                    new Request(`tezos://${{Ledger.selfAddress}}/nested`),
+                );
+                throw new Error("done");
+            }};
+            // {}"#,
+            "x".repeat(200_000)
+        );
+        let code = ParsedCode::try_from(code).unwrap();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::create(host.rt(), tx, &function, 0, Some(code), None).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let receipt = apply_operation(
+            &mut host,
+            Content::RunFunction(RunFunction {
+                uri: format!("tezos://{}/", function).parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 1_000_000,
+            }),
+        );
+        match receipt.inner {
+            Err(receipt::ReceiptError::RunFunctionFailed { gas_used, .. }) => {
+                assert!(gas_used > 200_000);
+                assert!(gas_used < 400_000);
+            }
+            inner => panic!("Expected a failed run, got {inner:?}"),
+        }
+    }
+
+    #[test]
+    fn parsing_is_charged_gas() {
+        let mut host = JstzMockHost::default();
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+
+        let code = format!(
+            "export default () => new Response(\"ok\");\n// {}",
+            "x".repeat(100_000)
+        );
+        let code = ParsedCode::try_from(code).unwrap();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::create(host.rt(), tx, &function, 0, Some(code), None).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let receipt = apply_operation(
+            &mut host,
+            Content::RunFunction(RunFunction {
+                uri: format!("tezos://{}/", function).parse().unwrap(),
+                method: http::Method::GET,
+                headers: http::HeaderMap::new(),
+                body: None,
+                gas_limit: 100_000,
+            }),
+        );
+        match receipt.inner {
+            Err(receipt::ReceiptError::RunFunctionFailed {
+                error, gas_used, ..
+            }) => {
+                assert_eq!(*error, receipt::ReceiptError::GasLimitExceeded);
+                assert!(gas_used > 100_000);
+            }
+            inner => panic!("Expected the run to exceed its gas limit, got {inner:?}"),
+        }
+        assert_eq!(run_function(&mut host, &function, 1_000_000), "ok");
+    }
+
+    #[test]
+    fn smart_function_creates_at_salted_address() {
+        let mut host = JstzMockHost::default();
+        let child = "export default () => new Response();";
+        let body = call_function(
+            &mut host,
+            &format!(
+                r#"export default async () => {{
+                    const address = await SmartFunction.create({child:?}, 0n, {{
+                        salt: "salt",
+                    }});
+                    return new Response(address);
+                }}"#
+            ),
+            1_000_000,
+        );
+
+        let (_, function_pk) = keypair_from_passphrase("function").unwrap();
+        let function = Address::try_from(&function_pk).unwrap();
+        let code = ParsedCode::try_from(child.to_string()).unwrap();
+        let expected = salted_address(&function, b"salt", &code.hash()).unwrap();
+        assert_eq!(body, expected.to_base58());
+    }
+
+    #[test]
+    fn salted_deployment_keeps_funds_sent_to_its_address() {
+        let mut host = JstzMockHost::default();
+        let (_, caller_pk) = keypair_from_passphrase("caller").unwrap();
+        let caller = Address::try_from(&caller_pk).unwrap();
+        let code = ParsedCode::try_from(
+            "export default () => new Response(\"deployed\");".to_string(),
+        )
+        .unwrap();
+        let address = salted_address(&caller, b"salt", &code.hash()).unwrap();
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::deposit(host.rt(), tx, &address, 10).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        let deploy = || {
+            Content::DeployFunction(DeployFunction {
+                function_code: code.clone(),
+                account_credit: 0,
+                salt: Some(b"salt".to_vec()),
+                metadata: None,
+                gas_limit: None,
+                owner: None,
+            })
+        };
+        let receipt = apply_operation(&mut host, deploy());
+        match receipt.inner {
+            Ok(ReceiptContent::DeployFunction(deploy)) => {
+                assert_eq!(deploy.address, address)
+            }
+            inner => panic!("Expected deploy function receipt, got {inner:?}"),
+        }
+        assert_eq!(run_function(&mut host, &address, 1_000_000), "deployed");
+        tx.begin();
+        assert_eq!(Account::balance(host.rt(), tx, &address).unwrap(), 10);
+        tx.commit(host.rt()).unwrap();
+
+        let receipt = apply_operation(&mut host, deploy());
+        assert_eq!(
+            receipt.inner.unwrap_err(),
+            receipt::ReceiptError::AlreadyDeployed
+        );
+    }
+
+    #[test]
+    fn created_smart_function_is_upgradable_by_designated_owner() {
+        let mut host = JstzMockHost::default();
+        let child = "export default () => new Response();";
+        let addresses = call_function(
+            &mut host,
+            &format!(
+                r#"export default async (request) => {{
+                    const owned = await SmartFunction.create({child:?}, 0n, {{
+                        owner: request.headers.get("Referer"),
+                    }});
+                    const unowned = await SmartFunction.create({child:?}, 0n, {{
+                        salt: "unowned",
+                    }});
+                    return new Response(`${{owned}},${{unowned}}`);
+                }}"#
+            ),
+            1_000_000,
+        );
+        let (owned, unowned) = addresses.split_once(',').unwrap();
+        let owned = Address::from_base58(owned).unwrap();
+        let unowned = Address::from_base58(unowned).unwrap();
+
+        let upgrade = |address: &Address| {
+            Content::UpgradeFunction(UpgradeFunction {
+                address: address.clone(),
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response(\"upgraded\");".to_string(),
+                )
+                .unwrap(),
+            })
+        };
+        let receipt = apply_operation(&mut host, upgrade(&owned));
+        assert!(receipt.inner.is_ok(), "{:?}", receipt.inner);
+        assert_eq!(run_function(&mut host, &owned, 1_000_000), "upgraded");
+
+        let receipt = apply_operation(&mut host, upgrade(&unowned));
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::UnauthorizedUpgrade)
+        ));
+    }
+
+    #[test]
+    fn init_runs_at_deploy() {
+        let mut host = JstzMockHost::default();
+        let receipt = deploy_function(
+            &mut host,
+            r#"export const init = (request) => {
+                Kv.set("deployer", request.headers.get("Referer"));
+                return new Response();
+            };
+            export default () => new Response(Kv.get("deployer"));"#,
+            Some(1_000_000),
+        );
+        let address = match receipt.inner {
+            Ok(ReceiptContent::DeployFunction(deploy)) => deploy.address,
+            inner => panic!("Expected deploy function receipt, got {inner:?}"),
+        };
+
+        let (_, pk) = keypair_from_passphrase("caller").unwrap();
+        let deployer = Address::try_from(&pk).unwrap();
+        assert_eq!(
+            run_function(&mut host, &address, 1_000_000),
+            deployer.to_base58()
+        );
+    }
+
+    #[test]
+    fn failing_init_aborts_deployment() {
+        let mut host = JstzMockHost::default();
+        let receipt = deploy_function(
+            &mut host,
+            r#"export const init = () => new Response(null, { status: 500 });
+            export default () => new Response();"#,
+            Some(1_000_000),
+        );
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::InitFailed)
+        ));
+
+        let receipt = deploy_function(
+            &mut host,
+            r#"export const init = () => { throw new Error("init"); };
+            export default () => new Response();"#,
+            Some(1_000_000),
+        );
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::JsException { .. })
+        ));
+    }
+
+    #[test]
+    fn init_runs_within_gas_limit() {
+        let mut host = JstzMockHost::default();
+        let code = r#"export const init = () => new Response(null, { status: 500 });
+            export default () => new Response();"#;
+
+        // Without a gas limit, `init` runs with the default one
+        let receipt = deploy_function(&mut host, code, None);
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::InitFailed)
+        ));
+        let receipt = deploy_function(
+            &mut host,
+            "export const init = () => { while (true) {} };",
+            None,
+        );
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::GasLimitExceeded)
+        ));
+
+        let receipt = deploy_function(
+            &mut host,
+            "export const init = () => { while (true) {} };",
+            Some(10_000),
+        );
+        assert!(matches!(
+            receipt.inner,
+            Err(receipt::ReceiptError::GasLimitExceeded)
+        ));
+    }
+
+    #[test]
+    fn deploy_records_metadata() {
+        let mut host = JstzMockHost::default();
+        let metadata = FunctionMetadata {
+            name: Some("hello".to_string()),
+            version: Some("1.0.0".to_string()),
+            bundler: Some("esbuild 0.19.5".to_string()),
+            ..Default::default()
+        };
+        let receipt = apply_operation(
+            &mut host,
+            Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response();".to_string(),
+                )
+                .unwrap(),
+                account_credit: 0,
+                salt: None,
+                metadata: Some(metadata.clone()),
+                gas_limit: None,
+                owner: None,
+            }),
+        );
+        let address = match receipt.inner {
+            Ok(ReceiptContent::DeployFunction(deploy)) => deploy.address,
+            inner => panic!("Expected deploy function receipt, got {inner:?}"),
+        };
+
+        let tx = &mut Transaction::default();
+        tx.begin();
+        let stored = Account::metadata(host.rt(), tx, &address).unwrap();
+        assert_eq!(stored, Some(&metadata));
+    }
 }
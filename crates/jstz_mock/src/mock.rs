@@ -4,6 +4,7 @@ use jstz_core::{host::HostRuntime, kv::Storage};
 use jstz_crypto::hash::Blake2b;
 use tezos_crypto_rs::hash::ContractKt1Hash;
 use tezos_smart_rollup::{
+    inbox::ExternalMessageFrame,
     michelson::{
         ticket::{FA2_1Ticket, Ticket},
         MichelsonBytes, MichelsonContract, MichelsonNat, MichelsonOption, MichelsonPair,
@@ -45,6 +46,13 @@ impl JstzMockHost {
         self.0.add_transfer(payload, &metadata)
     }
 
+    /// Adds an external message targetting the mock rollup
+    pub fn add_external_message(&mut self, contents: Vec<u8>) {
+        let address = self.0.reveal_metadata().address();
+        self.0
+            .add_external(ExternalMessageFrame::Targetted { address, contents })
+    }
+
     pub fn get_ticketer(&mut self) -> ContractKt1Hash {
         ContractKt1Hash::from_base58_check(NATIVE_TICKETER).unwrap()
     }
@@ -7,7 +7,7 @@ use actix_web::{
 };
 use anyhow::anyhow;
 use jstz_api::KvValue;
-use jstz_proto::context::account::Account;
+use jstz_proto::context::account::{Account, FunctionMetadata};
 use octez::OctezRollupClient;
 
 use crate::Result;
@@ -51,18 +51,74 @@ async fn code(
 
     let value = rollup_client.get_value(&key).await?;
 
-    let code = match value {
-        Some(value) => {
-            bincode::deserialize::<Account>(&value)
-                .map_err(|_| anyhow!("Failed to deserialize account"))?
-                .function_code
-        }
+    let account = match value {
+        Some(value) => bincode::deserialize::<Account>(&value)
+            .map_err(|_| anyhow!("Failed to deserialize account"))?,
         None => return Ok(HttpResponse::NotFound().finish()),
     };
 
+    let code = match (account.code_hash, account.legacy_code) {
+        (Some(code_hash), _) => {
+            let key = format!("/jstz_code/{}", code_hash.to_string());
+            match rollup_client.get_value(&key).await? {
+                Some(value) => Some(
+                    bincode::deserialize::<String>(&value)
+                        .map_err(|_| anyhow!("Failed to deserialize code"))?,
+                ),
+                None => Err(anyhow!("Missing code {}", code_hash.to_string()))?,
+            }
+        }
+        (None, legacy_code) => legacy_code.map(String::from),
+    };
+
     Ok(HttpResponse::Ok().json(code))
 }
 
+/// Returns the (hex-encoded) hash of a smart function's code. Smart functions
+/// deployed from the same code share the same hash.
+#[get("/{address}/code_hash")]
+async fn code_hash(
+    rollup_client: Data<OctezRollupClient>,
+    path: Path<String>,
+) -> Result<impl Responder> {
+    let key = format!("/jstz_account/{}", path.into_inner());
+
+    let value = rollup_client.get_value(&key).await?;
+
+    let code_hash = match value {
+        Some(value) => bincode::deserialize::<Account>(&value)
+            .map_err(|_| anyhow!("Failed to deserialize account"))?
+            .code_hash(),
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    Ok(HttpResponse::Ok().json(code_hash.map(|code_hash| code_hash.to_string())))
+}
+
+#[get("/{address}/metadata")]
+async fn metadata(
+    rollup_client: Data<OctezRollupClient>,
+    path: Path<String>,
+) -> Result<impl Responder> {
+    let address = path.into_inner();
+
+    let key = format!("/jstz_account/{}", address);
+    if rollup_client.get_value(&key).await?.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let key = format!("/jstz_account_metadata/{}", address);
+    let metadata = match rollup_client.get_value(&key).await? {
+        Some(value) => Some(
+            bincode::deserialize::<FunctionMetadata>(&value)
+                .map_err(|_| anyhow!("Failed to deserialize metadata"))?,
+        ),
+        None => None,
+    };
+
+    Ok(HttpResponse::Ok().json(metadata))
+}
+
 #[get("/{address}/balance")]
 async fn balance(
     rollup_client: Data<OctezRollupClient>,
@@ -135,6 +191,8 @@ impl Service for AccountsService {
         let scope = Scope::new("/accounts")
             .service(nonce)
             .service(code)
+            .service(code_hash)
+            .service(metadata)
             .service(balance)
             .service(kv)
             .service(kv_subkeys);
@@ -4,28 +4,38 @@ use actix_web::{
     HttpResponse, Responder, Scope,
 };
 use anyhow::anyhow;
-use jstz_proto::{operation::SignedOperation, receipt::Receipt};
+use jstz_proto::{
+    operation::{
+        chunk::{
+            ExternalMessage, EXTERNAL_MESSAGE_TAG, EXTERNAL_MESSAGE_VERSION,
+            MAX_OPERATION_SIZE,
+        },
+        SignedOperation,
+    },
+    receipt::Receipt,
+};
 use octez::OctezRollupClient;
 use tezos_data_encoding::enc::BinWriter;
-use tezos_smart_rollup::inbox::ExternalMessageFrame;
+use tezos_smart_rollup::{inbox::ExternalMessageFrame, types::SmartRollupAddress};
 
 use crate::Result;
 
 use super::Service;
 
-#[post("")]
-async fn inject(
-    rollup_client: Data<OctezRollupClient>,
-    operation: web::Json<SignedOperation>,
-) -> Result<impl Responder> {
-    let encoded_operation = bincode::serialize(&operation)
-        .map_err(|_| anyhow!("Failed to serialize operation"))?;
+/// The maximum size of an external inbox message, frame included
+const MAX_EXTERNAL_MESSAGE_SIZE: usize = 4096;
 
-    let address = rollup_client.get_rollup_address().await?;
+fn frame_message(
+    address: &SmartRollupAddress,
+    message: &ExternalMessage,
+) -> Result<Vec<u8>> {
+    let mut contents = vec![EXTERNAL_MESSAGE_TAG, EXTERNAL_MESSAGE_VERSION];
+    bincode::serialize_into(&mut contents, message)
+        .map_err(|_| anyhow!("Failed to serialize operation"))?;
 
     let message_frame = ExternalMessageFrame::Targetted {
-        address,
-        contents: encoded_operation,
+        address: address.clone(),
+        contents,
     };
 
     let mut binary_contents = Vec::new();
@@ -33,7 +43,40 @@ async fn inject(
         .bin_write(&mut binary_contents)
         .map_err(|_| anyhow!("Failed to write binary frame"))?;
 
-    rollup_client.batcher_injection([binary_contents]).await?;
+    Ok(binary_contents)
+}
+
+#[post("")]
+async fn inject(
+    rollup_client: Data<OctezRollupClient>,
+    operation: web::Json<SignedOperation>,
+) -> Result<impl Responder> {
+    let address = rollup_client.get_rollup_address().await?;
+
+    let operation = operation.into_inner();
+    let encoded_operation = bincode::serialize(&operation)
+        .map_err(|_| anyhow!("Failed to serialize operation"))?;
+    if encoded_operation.len() > MAX_OPERATION_SIZE as usize {
+        return Err(anyhow!(
+            "Operation exceeds the maximum size of {MAX_OPERATION_SIZE} bytes"
+        )
+        .into());
+    }
+
+    // Operations that do not fit in a single inbox message are split into
+    // chunks, which the kernel reassembles before executing the operation
+    let chunks = ExternalMessage::split(&operation, &encoded_operation);
+    let message = frame_message(&address, &ExternalMessage::Operation(operation))?;
+    let messages = if message.len() <= MAX_EXTERNAL_MESSAGE_SIZE {
+        vec![message]
+    } else {
+        chunks
+            .iter()
+            .map(|chunk| frame_message(&address, chunk))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    rollup_client.batcher_injection(messages).await?;
 
     Ok(HttpResponse::Ok())
 }
@@ -56,11 +99,23 @@ async fn receipt(
     Ok(HttpResponse::Ok().json(receipt))
 }
 
+#[get("/rollup_address")]
+async fn rollup_address(
+    rollup_client: Data<OctezRollupClient>,
+) -> Result<impl Responder> {
+    let address = rollup_client.get_rollup_address().await?;
+
+    Ok(HttpResponse::Ok().json(address.hash()))
+}
+
 pub struct OperationsService;
 
 impl Service for OperationsService {
     fn configure(cfg: &mut ServiceConfig) {
-        let scope = Scope::new("/operations").service(inject).service(receipt);
+        let scope = Scope::new("/operations")
+            .service(inject)
+            .service(rollup_address)
+            .service(receipt);
 
         cfg.service(scope);
     }
@@ -0,0 +1,81 @@
+use std::ops::Deref;
+
+use boa_engine::{
+    js_string,
+    object::{FunctionObjectBuilder, ObjectInitializer},
+    property::Attribute,
+    Context, JsResult, JsValue, NativeFunction,
+};
+use jstz_core::{host::HostRuntime, runtime, value::IntoJs};
+
+use crate::{context::block::Block, error::Result};
+
+// Jstz.block.level
+// Jstz.block.timestamp
+// Jstz.block.predecessor
+
+struct BlockInfo {
+    level: u32,
+    timestamp: i64,
+    predecessor: Option<String>,
+}
+
+impl BlockInfo {
+    fn current(rt: &impl HostRuntime) -> Result<Self> {
+        Ok(Self {
+            level: Block::level(rt)?,
+            timestamp: Block::timestamp(rt)?,
+            predecessor: Block::predecessor(rt)?.map(|hash| hash.to_base58_check()),
+        })
+    }
+}
+
+pub struct BlockApi;
+
+impl BlockApi {
+    const NAME: &'static str = "Jstz";
+
+    fn block(
+        _this: &JsValue,
+        _args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let info = runtime::with_js_hrt(|hrt| BlockInfo::current(hrt.deref()))?;
+
+        let predecessor = match info.predecessor {
+            Some(predecessor) => predecessor.into_js(context),
+            None => JsValue::null(),
+        };
+
+        let block = ObjectInitializer::new(context)
+            .property(js_string!("level"), info.level, Attribute::READONLY)
+            .property(
+                js_string!("timestamp"),
+                info.timestamp as f64,
+                Attribute::READONLY,
+            )
+            .property(js_string!("predecessor"), predecessor, Attribute::READONLY)
+            .build();
+
+        Ok(block.into())
+    }
+}
+
+impl jstz_core::Api for BlockApi {
+    fn init(self, context: &mut Context<'_>) {
+        let block = FunctionObjectBuilder::new(
+            context.realm(),
+            NativeFunction::from_fn_ptr(Self::block),
+        )
+        .name(js_string!("get block"))
+        .build();
+
+        let jstz = ObjectInitializer::new(context)
+            .accessor(js_string!("block"), Some(block), None, Attribute::all())
+            .build();
+
+        context
+            .register_global_property(js_string!(Self::NAME), jstz, Attribute::all())
+            .expect("The jstz object shouldn't exist yet");
+    }
+}
@@ -14,13 +14,17 @@ use jstz_core::{
 };
 
 use crate::{
-    context::account::{Account, Address, Amount},
+    context::{
+        account::{Account, Address, Amount},
+        outbox::{Outbox, OutboxMessageId},
+    },
     error::Result,
 };
 
 // Ledger.selfAddress
 // Ledger.balance(pkh)
 // Ledger.transfer(dst, amount)
+// Ledger.withdraw(l1_address, amount)
 
 struct Ledger {
     address: Address,
@@ -58,6 +62,18 @@ impl Ledger {
 
         Ok(())
     }
+
+    fn withdraw(
+        &self,
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+        receiver: &Address,
+        amount: Amount,
+    ) -> Result<OutboxMessageId> {
+        Account::withdraw(rt, tx, &self.address, amount)?;
+
+        Outbox::push_withdrawal(rt, tx, receiver, amount)
+    }
 }
 
 pub struct LedgerApi {
@@ -133,6 +149,30 @@ impl LedgerApi {
 
         Ok(JsValue::undefined())
     }
+
+    fn withdraw(
+        this: &JsValue,
+        args: &[JsValue],
+        context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let ledger = Ledger::try_from_js(this)?;
+        let receiver = js_value_to_pkh(args.get_or_undefined(0))?;
+        let amount = args
+            .get_or_undefined(1)
+            .as_number()
+            .ok_or_else(JsNativeError::typ)?;
+
+        let OutboxMessageId { level, index } = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            ledger.withdraw(hrt.deref(), tx, &receiver, amount as Amount)
+        })?;
+
+        let message_id = ObjectInitializer::new(context)
+            .property(js_string!("level"), level, Attribute::READONLY)
+            .property(js_string!("index"), index, Attribute::READONLY)
+            .build();
+
+        Ok(message_id.into())
+    }
 }
 
 impl jstz_core::Api for LedgerApi {
@@ -161,6 +201,11 @@ impl jstz_core::Api for LedgerApi {
             js_string!("transfer"),
             3,
         )
+        .function(
+            NativeFunction::from_fn_ptr(Self::withdraw),
+            js_string!("withdraw"),
+            2,
+        )
         .build();
 
         context
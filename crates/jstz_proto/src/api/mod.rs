@@ -1,5 +1,9 @@
+mod block;
 mod ledger;
 mod smart_function;
+mod tickets;
 
+pub use block::BlockApi;
 pub use ledger::LedgerApi;
 pub use smart_function::{SmartFunctionApi, TraceData};
+pub use tickets::TicketsApi;
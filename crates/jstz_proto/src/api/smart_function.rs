@@ -6,7 +6,7 @@ use boa_engine::{
     property::Attribute,
     Context, JsArgs, JsError, JsNativeError, JsResult, JsValue, NativeFunction,
 };
-use jstz_api::http::request::Request;
+use jstz_api::{http::request::Request, idl::JsBufferSource};
 use jstz_core::{
     host::HostRuntime, host_defined, kv::Transaction, native::JsNativeObject, runtime,
     value::IntoJs,
@@ -21,6 +21,8 @@ use crate::{
 
 use boa_gc::{empty_trace, Finalize, GcRefMut, Trace};
 
+use super::ledger::js_value_to_pkh;
+
 pub struct TraceData {
     pub address: Address,
     pub operation_hash: OperationHash,
@@ -61,19 +63,31 @@ impl SmartFunction {
         tx: &mut Transaction,
         function_code: ParsedCode,
         initial_balance: Amount,
+        salt: Option<Vec<u8>>,
+        owner: Option<Address>,
     ) -> Result<String> {
         // 1. Check if the associated account has sufficient balance
         {
             let balance = Account::balance(hrt, tx, &self.address)?;
 
             if balance < initial_balance {
-                return Err(Error::BalanceOverflow);
+                return Err(Error::InsufficientFunds {
+                    balance,
+                    amount: initial_balance,
+                });
             }
         } // The mutable borrow of `tx` in `balance` is released here
 
         // 2. Deploy the smart function
-        let address =
-            Script::deploy(hrt, tx, &self.address, function_code, initial_balance)?; // The mutable borrow of `tx` in `Script::deploy` is released here
+        let (address, _) = Script::deploy(
+            hrt,
+            tx,
+            &self.address,
+            function_code,
+            initial_balance,
+            salt.as_deref(),
+            owner,
+        )?; // The mutable borrow of `tx` in `Script::deploy` is released here
 
         // 3. Increment nonce of current account
         {
@@ -111,6 +125,46 @@ impl SmartFunction {
     }
 }
 
+/// Reads the salt from the options of `SmartFunction.create`. A salt is either
+/// a string (encoded as UTF-8) or a buffer source.
+fn salt_from_options(
+    options: Option<&JsValue>,
+    context: &mut Context<'_>,
+) -> JsResult<Option<Vec<u8>>> {
+    let Some(options) = options.and_then(JsValue::as_object) else {
+        return Ok(None);
+    };
+
+    let salt = options.get(js_string!("salt"), context)?;
+    if salt.is_undefined() {
+        return Ok(None);
+    }
+    if let Some(salt) = salt.as_string() {
+        return Ok(Some(salt.to_std_string_escaped().into_bytes()));
+    }
+
+    let salt: JsBufferSource = salt.try_js_into(context)?;
+    Ok(Some(salt.to_vec(context)?))
+}
+
+/// Reads the owner from the options of `SmartFunction.create`. Without an
+/// owner, the created smart function can never be upgraded.
+fn owner_from_options(
+    options: Option<&JsValue>,
+    context: &mut Context<'_>,
+) -> JsResult<Option<Address>> {
+    let Some(options) = options.and_then(JsValue::as_object) else {
+        return Ok(None);
+    };
+
+    let owner = options.get(js_string!("owner"), context)?;
+    if owner.is_null_or_undefined() {
+        return Ok(None);
+    }
+
+    Ok(Some(js_value_to_pkh(&owner)?))
+}
+
 pub struct SmartFunctionApi {
     pub address: Address,
 }
@@ -166,6 +220,7 @@ impl SmartFunctionApi {
 
         let initial_balance = match args.get(1) {
             None => 0,
+            Some(balance) if balance.is_undefined() => 0,
             Some(balance) => balance
                 .to_big_uint64(context)?
                 .iter_u64_digits()
@@ -173,6 +228,9 @@ impl SmartFunctionApi {
                 .unwrap_or_default(),
         };
 
+        let salt = salt_from_options(args.get(2), context)?;
+        let owner = owner_from_options(args.get(2), context)?;
+
         let promise = JsPromise::new(
             move |resolvers, context| {
                 let address = runtime::with_js_hrt_and_tx(|hrt, tx| {
@@ -181,6 +239,8 @@ impl SmartFunctionApi {
                         tx,
                         parsed_code,
                         initial_balance as Amount,
+                        salt,
+                        owner,
                     )
                 })?;
 
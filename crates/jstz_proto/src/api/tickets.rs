@@ -0,0 +1,224 @@
+use boa_engine::{
+    js_string,
+    object::{Object, ObjectInitializer},
+    property::Attribute,
+    Context, JsArgs, JsNativeError, JsResult, JsString, JsValue, NativeFunction,
+};
+use boa_gc::{empty_trace, Finalize, GcRefMut, Trace};
+
+use jstz_core::{host::HostRuntime, kv::Transaction, runtime};
+use jstz_crypto::hash::Blake2b;
+
+use crate::{
+    context::{
+        account::{Address, Amount},
+        ticket_table::TicketTable,
+    },
+    error::Result,
+};
+
+use super::ledger::js_value_to_pkh;
+
+// Tickets.balance(owner, ticketHash)
+// Tickets.transfer(dst, ticketHash, amount)
+
+struct Tickets {
+    address: Address,
+}
+
+impl Finalize for Tickets {}
+
+unsafe impl Trace for Tickets {
+    empty_trace!();
+}
+
+impl Tickets {
+    fn balance(
+        rt: &mut impl HostRuntime,
+        tx: &mut Transaction,
+        owner: &Address,
+        ticket_hash: &Blake2b,
+    ) -> Result<Amount> {
+        TicketTable::get_balance(rt, tx, owner, ticket_hash)
+    }
+
+    /// Moves tickets owned by the smart function to `dst`. The smart function
+    /// can only ever spend its own tickets.
+    fn transfer(
+        &self,
+        rt: &mut impl HostRuntime,
+        tx: &mut Transaction,
+        dst: &Address,
+        ticket_hash: &Blake2b,
+        amount: Amount,
+    ) -> Result<()> {
+        TicketTable::sub(rt, tx, &self.address, ticket_hash, amount)?;
+        TicketTable::add(rt, tx, dst, ticket_hash, amount)?;
+
+        Ok(())
+    }
+}
+
+pub struct TicketsApi {
+    pub address: Address,
+}
+
+fn js_value_to_ticket_hash(value: &JsValue) -> Result<Blake2b> {
+    let ticket_hash = value
+        .as_string()
+        .ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message("Failed to convert js value into rust type `String`")
+        })
+        .map(JsString::to_std_string_escaped)?;
+
+    Ok(Blake2b::from_hex(&ticket_hash)?)
+}
+
+impl Tickets {
+    fn try_from_js(value: &JsValue) -> JsResult<GcRefMut<'_, Object, Self>> {
+        value
+            .as_object()
+            .and_then(|obj| obj.downcast_mut::<Self>())
+            .ok_or_else(|| {
+                JsNativeError::typ()
+                    .with_message("Failed to convert js value into rust type `Tickets`")
+                    .into()
+            })
+    }
+}
+
+impl TicketsApi {
+    const NAME: &'static str = "Tickets";
+
+    fn balance(
+        _this: &JsValue,
+        args: &[JsValue],
+        _context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let owner = js_value_to_pkh(args.get_or_undefined(0))?;
+        let ticket_hash = js_value_to_ticket_hash(args.get_or_undefined(1))?;
+
+        let balance = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            Tickets::balance(hrt, tx, &owner, &ticket_hash)
+        })?;
+
+        Ok(balance.into())
+    }
+
+    fn transfer(
+        this: &JsValue,
+        args: &[JsValue],
+        _context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        let tickets = Tickets::try_from_js(this)?;
+        let dst = js_value_to_pkh(args.get_or_undefined(0))?;
+        let ticket_hash = js_value_to_ticket_hash(args.get_or_undefined(1))?;
+        let amount = args
+            .get_or_undefined(2)
+            .as_number()
+            .ok_or_else(JsNativeError::typ)?;
+
+        runtime::with_js_hrt_and_tx(|hrt, tx| {
+            tickets.transfer(hrt, tx, &dst, &ticket_hash, amount as Amount)
+        })?;
+
+        Ok(JsValue::undefined())
+    }
+}
+
+impl jstz_core::Api for TicketsApi {
+    fn init(self, context: &mut boa_engine::Context<'_>) {
+        let tickets = ObjectInitializer::with_native(
+            Tickets {
+                address: self.address,
+            },
+            context,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::balance),
+            js_string!("balance"),
+            2,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(Self::transfer),
+            js_string!("transfer"),
+            3,
+        )
+        .build();
+
+        context
+            .register_global_property(js_string!(Self::NAME), tickets, Attribute::all())
+            .expect("The tickets object shouldn't exist yet");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jstz_core::kv::Transaction;
+    use jstz_crypto::{keypair_from_passphrase, public_key_hash::PublicKeyHash};
+    use jstz_mock::mock::{self, JstzMockHost};
+
+    use super::Tickets;
+    use crate::context::ticket_table::TicketTable;
+
+    fn account2() -> PublicKeyHash {
+        let (_, pk) = keypair_from_passphrase("account2").unwrap();
+        PublicKeyHash::try_from(&pk).unwrap()
+    }
+
+    #[test]
+    fn transfer_moves_owned_tickets() {
+        let mut host = JstzMockHost::default();
+        let mut tx = Transaction::default();
+        let ticket_hash = mock::ticket_hash1();
+        let tickets = Tickets {
+            address: mock::account1(),
+        };
+
+        tx.begin();
+        TicketTable::add(host.rt(), &mut tx, &tickets.address, &ticket_hash, 100)
+            .unwrap();
+        tickets
+            .transfer(host.rt(), &mut tx, &account2(), &ticket_hash, 40)
+            .unwrap();
+
+        let src =
+            Tickets::balance(host.rt(), &mut tx, &tickets.address, &ticket_hash).unwrap();
+        let dst =
+            Tickets::balance(host.rt(), &mut tx, &account2(), &ticket_hash).unwrap();
+        assert_eq!(src, 60);
+        assert_eq!(dst, 40);
+
+        let err = tickets
+            .transfer(host.rt(), &mut tx, &account2(), &ticket_hash, 100)
+            .expect_err("Expected error");
+        assert_eq!(err.to_string(), "InsufficientFunds");
+    }
+
+    #[test]
+    fn transfer_rolls_back_with_transaction() {
+        let mut host = JstzMockHost::default();
+        let mut tx = Transaction::default();
+        let ticket_hash = mock::ticket_hash1();
+        let tickets = Tickets {
+            address: mock::account1(),
+        };
+
+        tx.begin();
+        TicketTable::add(host.rt(), &mut tx, &tickets.address, &ticket_hash, 100)
+            .unwrap();
+        tx.begin();
+        tickets
+            .transfer(host.rt(), &mut tx, &account2(), &ticket_hash, 40)
+            .unwrap();
+        tx.rollback().unwrap();
+
+        let src =
+            Tickets::balance(host.rt(), &mut tx, &tickets.address, &ticket_hash).unwrap();
+        let dst =
+            Tickets::balance(host.rt(), &mut tx, &account2(), &ticket_hash).unwrap();
+        assert_eq!(src, 100);
+        assert_eq!(dst, 0);
+    }
+}
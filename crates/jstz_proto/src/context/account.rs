@@ -9,9 +9,13 @@ use jstz_core::{
     host::HostRuntime,
     kv::{Entry, Transaction},
 };
-use jstz_crypto::public_key_hash::PublicKeyHash;
+use jstz_crypto::{hash::Blake2b, public_key_hash::PublicKeyHash};
 
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use tezos_smart_rollup::storage::path::{self, OwnedPath, RefPath};
 
 pub type Address = PublicKeyHash;
@@ -29,6 +33,10 @@ impl Nonce {
     pub fn increment(&mut self) {
         self.0 += 1
     }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
 }
 
 impl Display for Nonce {
@@ -60,11 +68,214 @@ impl TryFrom<String> for ParsedCode {
     }
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+const CODE_PATH: RefPath = RefPath::assert_from(b"/jstz_code");
+const CODE_REFS_PATH: RefPath = RefPath::assert_from(b"/jstz_code_refs");
+
+impl ParsedCode {
+    /// Returns the hash of the code
+    pub fn hash(&self) -> Blake2b {
+        Blake2b::from(self.0.as_bytes())
+    }
+
+    /// Returns the path of the code with the given hash
+    pub fn path(hash: &Blake2b) -> Result<OwnedPath> {
+        let code_path = OwnedPath::try_from(format!("/{}", hash.to_string()))?;
+
+        Ok(path::concat(&CODE_PATH, &code_path)?)
+    }
+
+    /// Returns the path of the number of accounts referencing the code with
+    /// the given hash
+    fn refs_path(hash: &Blake2b) -> Result<OwnedPath> {
+        let refs_path = OwnedPath::try_from(format!("/{}", hash.to_string()))?;
+
+        Ok(path::concat(&CODE_REFS_PATH, &refs_path)?)
+    }
+
+    /// Stores the code (unless identical code is already stored) and counts a
+    /// new reference to it, returning its hash
+    fn store(self, hrt: &impl HostRuntime, tx: &mut Transaction) -> Result<Blake2b> {
+        let hash = self.hash();
+        let path = Self::path(&hash)?;
+        if !tx.contains_key(hrt, &path)? {
+            tx.insert(path, self)?;
+        }
+        *tx.entry::<u64>(hrt, Self::refs_path(&hash)?)?
+            .or_insert_default() += 1;
+        Ok(hash)
+    }
+
+    /// Drops a reference to the code with the given hash, removing the code
+    /// once no account references it
+    fn release(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        hash: &Blake2b,
+    ) -> Result<()> {
+        let refs_path = Self::refs_path(hash)?;
+        let refs = tx.entry::<u64>(hrt, refs_path.clone())?.or_insert_default();
+        *refs = refs.saturating_sub(1);
+        if *refs == 0 {
+            tx.remove(refs_path)?;
+            tx.remove(Self::path(hash)?)?;
+        }
+        Ok(())
+    }
+
+    fn load<'a>(
+        hrt: &impl HostRuntime,
+        tx: &'a mut Transaction,
+        hash: &Blake2b,
+    ) -> Result<Option<&'a Self>> {
+        Ok(tx.get::<Self>(hrt, Self::path(hash)?)?)
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct Account {
     pub nonce: Nonce,
     pub amount: Amount,
-    pub function_code: Option<ParsedCode>,
+    /// The hash of the function code, which is stored once under `/jstz_code`
+    /// and shared by all accounts deploying the same code. The code is removed
+    /// once no account references it.
+    pub code_hash: Option<Blake2b>,
+    /// The address allowed to upgrade the function code (and to change its
+    /// owner). Functions without an owner cannot be upgraded.
+    pub owner: Option<Address>,
+    /// The code of a smart function deployed before code was stored under
+    /// `/jstz_code`. It is moved there the first time the account is accessed.
+    pub legacy_code: Option<ParsedCode>,
+}
+
+// Accounts were first encoded as `nonce amount function_code:option<string>`,
+// so the byte following the amount is the tag of an option (`0` or `1`).
+// Later encodings replace it with a version number, which lets accounts stored
+// in any encoding be decoded.
+const ACCOUNT_VERSION: u8 = 2;
+
+impl Serialize for Account {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut account = serializer.serialize_tuple(6)?;
+        account.serialize_element(&self.nonce)?;
+        account.serialize_element(&self.amount)?;
+        account.serialize_element(&ACCOUNT_VERSION)?;
+        account.serialize_element(&self.code_hash)?;
+        account.serialize_element(&self.owner)?;
+        account.serialize_element(&self.legacy_code)?;
+        account.end()
+    }
+}
+
+fn next_element<'de, A, T>(seq: &mut A, index: usize) -> result::Result<T, A::Error>
+where
+    A: SeqAccess<'de>,
+    T: Deserialize<'de>,
+{
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(index, &"an account"))
+}
+
+impl<'de> Deserialize<'de> for Account {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> result::Result<Self, D::Error> {
+        struct AccountVisitor;
+
+        impl<'de> Visitor<'de> for AccountVisitor {
+            type Value = Account;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                formatter.write_str("an account")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> result::Result<Account, A::Error> {
+                let nonce = next_element(&mut seq, 0)?;
+                let amount = next_element(&mut seq, 1)?;
+                let account = match next_element(&mut seq, 2)? {
+                    // Legacy encoding, without code
+                    0u8 => Account {
+                        nonce,
+                        amount,
+                        ..Default::default()
+                    },
+                    // Legacy encoding, with the code stored in the account
+                    1 => Account {
+                        nonce,
+                        amount,
+                        legacy_code: Some(ParsedCode(next_element(&mut seq, 3)?)),
+                        ..Default::default()
+                    },
+                    ACCOUNT_VERSION => Account {
+                        nonce,
+                        amount,
+                        code_hash: next_element(&mut seq, 3)?,
+                        owner: next_element(&mut seq, 4)?,
+                        legacy_code: next_element(&mut seq, 5)?,
+                    },
+                    version => {
+                        return Err(de::Error::custom(format!(
+                            "unknown account version {version}"
+                        )))
+                    }
+                };
+
+                Ok(account)
+            }
+        }
+
+        deserializer.deserialize_tuple(6, AccountVisitor)
+    }
+}
+
+/// Describes a smart function, as provided by its deployer. Explorers can
+/// rebuild the code from the published source with the recorded bundler and
+/// compare it against the code hash of the account.
+#[derive(Default, PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionMetadata {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// Hex-encoded Blake2b-256 hash of the original (unbundled) source
+    pub source_hash: Option<String>,
+    /// The bundler or compiler (and its version) that produced the code
+    pub bundler: Option<String>,
+}
+
+const METADATA_PATH: RefPath = RefPath::assert_from(b"/jstz_account_metadata");
+
+impl FunctionMetadata {
+    pub fn path(addr: &Address) -> Result<OwnedPath> {
+        let metadata_path = OwnedPath::try_from(format!("/{}", addr))?;
+
+        Ok(path::concat(&METADATA_PATH, &metadata_path)?)
+    }
+}
+
+/// Prefixes the data hashed into salted addresses. Nonce-derived addresses
+/// hash UTF-8 text, which never starts with `0xff`, so the two kinds of
+/// addresses can't collide.
+const SALTED_ADDRESS_PREFIX: u8 = 0xff;
+
+/// Returns the address of the smart function deployed by `deployer` with the
+/// given salt and code hash. Unlike nonce-derived addresses, it can be computed
+/// before the smart function is deployed.
+pub fn salted_address(
+    deployer: &Address,
+    salt: &[u8],
+    code_hash: &Blake2b,
+) -> Result<Address> {
+    // The deployer and code hash have a fixed length, so the salt is unambiguous
+    let data = [
+        &[SALTED_ADDRESS_PREFIX],
+        deployer.to_base58().as_bytes(),
+        salt,
+        code_hash.as_ref(),
+    ]
+    .concat();
+    Ok(Address::digest(&data)?)
 }
 
 const ACCOUNTS_PATH: RefPath = RefPath::assert_from(b"/jstz_account");
@@ -84,7 +295,22 @@ impl Account {
     where
         'a: 'b,
     {
-        let account_entry = tx.entry::<Self>(hrt, Self::path(addr)?)?;
+        let path = Self::path(addr)?;
+
+        // Migrate the code of legacy accounts to `/jstz_code`
+        let legacy_code = tx
+            .entry::<Self>(hrt, path.clone())?
+            .or_insert_default()
+            .legacy_code
+            .take();
+        if let Some(code) = legacy_code {
+            let code_hash = code.store(hrt, tx)?;
+            tx.entry::<Self>(hrt, path.clone())?
+                .or_insert_default()
+                .code_hash = Some(code_hash);
+        }
+
+        let account_entry = tx.entry::<Self>(hrt, path)?;
         Ok(account_entry.or_insert_default())
     }
 
@@ -97,7 +323,7 @@ impl Account {
         match tx.entry::<Self>(hrt, Self::path(addr)?)? {
             Entry::Occupied(ntry) => {
                 let acc: &Self = ntry.get();
-                hrt.write_debug(&format!("📜 already exists: {:?}\n", acc.function_code));
+                hrt.write_debug(&format!("📜 already exists: {:?}\n", acc.code_hash));
                 Err(Error::InvalidAddress)
             }
             Entry::Vacant(entry) => {
@@ -120,10 +346,30 @@ impl Account {
         hrt: &impl HostRuntime,
         tx: &'a mut Transaction,
         addr: &Address,
-    ) -> Result<Option<&'a mut String>> {
+    ) -> Result<Option<&'a String>> {
+        match Self::get_mut(hrt, tx, addr)?.code_hash.clone() {
+            Some(hash) => Ok(ParsedCode::load(hrt, tx, &hash)?.map(|code| &code.0)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the hash of the smart function's code, if any
+    pub fn function_code_hash(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+    ) -> Result<Option<Blake2b>> {
         let account = Self::get_mut(hrt, tx, addr)?;
-        let function_code = account.function_code.as_mut().map(|code| &mut code.0);
-        Ok(function_code)
+        Ok(account.code_hash())
+    }
+
+    /// Returns the hash of the code, whether it is stored under `/jstz_code`
+    /// or (for legacy accounts) in the account
+    pub fn code_hash(&self) -> Option<Blake2b> {
+        match (&self.code_hash, &self.legacy_code) {
+            (Some(hash), _) => Some(hash.clone()),
+            (None, code) => code.as_ref().map(ParsedCode::hash),
+        }
     }
 
     pub fn set_function_code(
@@ -132,10 +378,59 @@ impl Account {
         addr: &Address,
         function_code: String,
     ) -> Result<()> {
+        let previous_code_hash = Self::get_mut(hrt, tx, addr)?.code_hash.clone();
+        let code_hash = ParsedCode::try_from(function_code)?.store(hrt, tx)?;
+        if let Some(previous_code_hash) = previous_code_hash {
+            ParsedCode::release(hrt, tx, &previous_code_hash)?;
+        }
+        Self::get_mut(hrt, tx, addr)?.code_hash = Some(code_hash);
+        tx.remove(FunctionMetadata::path(addr)?)?;
+        Ok(())
+    }
+
+    /// Replaces the code of a deployed smart function, returning the hash of the
+    /// previous code. Only the owner of the smart function may upgrade it. The
+    /// metadata of the smart function described the previous code, so it is
+    /// removed.
+    pub fn upgrade_function_code(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        owner: &Address,
+        function_code: ParsedCode,
+    ) -> Result<Blake2b> {
         let account = Self::get_mut(hrt, tx, addr)?;
 
-        account.function_code = Some(function_code.try_into()?);
-        Ok(())
+        let previous_code_hash = match account.code_hash() {
+            None => return Err(Error::InvalidAddress),
+            Some(_) if account.owner.as_ref() != Some(owner) => {
+                return Err(Error::UnauthorizedUpgrade)
+            }
+            Some(code_hash) => code_hash,
+        };
+
+        let code_hash = function_code.store(hrt, tx)?;
+        ParsedCode::release(hrt, tx, &previous_code_hash)?;
+        Self::get_mut(hrt, tx, addr)?.code_hash = Some(code_hash);
+        tx.remove(FunctionMetadata::path(addr)?)?;
+        Ok(previous_code_hash)
+    }
+
+    /// Returns the metadata of the smart function, if its deployer provided any
+    pub fn metadata<'a>(
+        hrt: &impl HostRuntime,
+        tx: &'a mut Transaction,
+        addr: &Address,
+    ) -> Result<Option<&'a FunctionMetadata>> {
+        Ok(tx.get::<FunctionMetadata>(hrt, FunctionMetadata::path(addr)?)?)
+    }
+
+    pub fn set_metadata(
+        tx: &mut Transaction,
+        addr: &Address,
+        metadata: FunctionMetadata,
+    ) -> Result<()> {
+        Ok(tx.insert(FunctionMetadata::path(addr)?, metadata)?)
     }
 
     pub fn balance(
@@ -160,6 +455,26 @@ impl Account {
         Ok(())
     }
 
+    pub fn withdraw(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        addr: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        let account = Self::get_mut(hrt, tx, addr)?;
+
+        match account.amount.checked_sub(amount) {
+            Some(amt) => account.amount = amt,
+            None => {
+                return Err(Error::InsufficientFunds {
+                    balance: account.amount,
+                    amount,
+                })
+            }
+        }
+        Ok(())
+    }
+
     pub fn set_balance(
         hrt: &impl HostRuntime,
         tx: &mut Transaction,
@@ -178,11 +493,35 @@ impl Account {
         addr: &Address,
         amount: Amount,
         function_code: Option<ParsedCode>,
+        owner: Option<Address>,
     ) -> Result<()> {
+        if tx.contains_key(hrt, &Self::path(addr)?)? {
+            // An existing account without code (e.g. one that was sent funds
+            // before being deployed) can still be given code, keeping its
+            // balance and nonce. Only count a reference to the code if so.
+            let has_code = Self::get_mut(hrt, tx, addr)?.code_hash().is_some();
+            let code = match function_code {
+                Some(code) if !has_code => code,
+                _ => {
+                    hrt.write_debug(&format!("📜 already exists: {}\n", addr));
+                    return Err(Error::InvalidAddress);
+                }
+            };
+            let code_hash = code.store(hrt, tx)?;
+            let account = Self::get_mut(hrt, tx, addr)?;
+            account.amount += amount;
+            account.code_hash = Some(code_hash);
+            account.owner = owner;
+            return Ok(());
+        }
+        let code_hash = function_code.map(|code| code.store(hrt, tx)).transpose()?;
+
         Self {
             nonce: Nonce::default(),
             amount,
-            function_code,
+            code_hash,
+            owner,
+            legacy_code: None,
         }
         .try_insert(hrt, tx, addr)
     }
@@ -200,7 +539,12 @@ impl Account {
                 .or_insert_default();
             match src.amount.checked_sub(amt) {
                 Some(amt) => src.amount = amt,
-                None => return Err(Error::BalanceOverflow),
+                None => {
+                    return Err(Error::InsufficientFunds {
+                        balance: src.amount,
+                        amount: amt,
+                    })
+                }
             }
         }
 
@@ -219,6 +563,8 @@ impl Account {
 #[cfg(test)]
 mod test {
     use super::*;
+    use jstz_core::kv::Storage;
+    use tezos_smart_rollup::prelude::Runtime;
     use tezos_smart_rollup_mock::MockHost;
 
     #[test]
@@ -246,4 +592,271 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn withdraw_rejects_overdraft() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+
+        let pkh = PublicKeyHash::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+        Account::deposit(hrt, tx, &pkh, 10)?;
+
+        let err = Account::withdraw(hrt, tx, &pkh, 11).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InsufficientFunds {
+                balance: 10,
+                amount: 11
+            }
+        ));
+        assert_eq!(Account::balance(hrt, tx, &pkh)?, 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn upgrade_function_code_requires_owner() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+
+        tx.begin();
+
+        let owner = PublicKeyHash::from_base58("tz1XQjK1b3P72kMcHsoPhnAg3dvX1n8Ainty")
+            .expect("Could not parse pkh");
+        let address = PublicKeyHash::digest(b"smart function")?;
+        let code = |code: &str| ParsedCode::try_from(code.to_string()).unwrap();
+
+        Account::create(
+            hrt,
+            tx,
+            &address,
+            0,
+            Some(code("export default () => {}")),
+            Some(owner.clone()),
+        )?;
+
+        let result = Account::upgrade_function_code(
+            hrt,
+            tx,
+            &address,
+            &address,
+            code("export default () => 1"),
+        );
+        assert!(matches!(result, Err(Error::UnauthorizedUpgrade)));
+
+        let previous = Account::upgrade_function_code(
+            hrt,
+            tx,
+            &address,
+            &owner,
+            code("export default () => 2"),
+        )?;
+        assert_eq!(previous, code("export default () => {}").hash());
+        assert_eq!(
+            Account::function_code(hrt, tx, &address)?.cloned(),
+            Some("export default () => 2".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_accounts_are_decoded() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+
+        let address = PublicKeyHash::digest(b"legacy")?;
+        let code = "export default () => {}";
+
+        // nonce:u64 amount:u64 function_code:option<string>
+        let mut legacy = Vec::new();
+        legacy.extend_from_slice(&3u64.to_le_bytes());
+        legacy.extend_from_slice(&42u64.to_le_bytes());
+        legacy.push(1);
+        legacy.extend_from_slice(&(code.len() as u64).to_le_bytes());
+        legacy.extend_from_slice(code.as_bytes());
+        hrt.store_write_all(&Account::path(&address)?, &legacy)
+            .expect("Could not write account");
+
+        tx.begin();
+        Account::deposit(hrt, tx, &address, 1)?;
+        tx.commit(hrt)?;
+
+        tx.begin();
+        assert_eq!(Account::balance(hrt, tx, &address)?, 43);
+        assert_eq!(*Account::nonce(hrt, tx, &address)?, Nonce(3));
+        assert_eq!(
+            Account::function_code(hrt, tx, &address)?.cloned(),
+            Some(code.to_string())
+        );
+        assert_eq!(
+            Account::function_code_hash(hrt, tx, &address)?,
+            Some(Blake2b::from(code.as_bytes()))
+        );
+        tx.commit(hrt)?;
+
+        // The code was moved out of the account
+        let account = Storage::get::<Account>(hrt, &Account::path(&address)?)?
+            .expect("Expected account");
+        assert!(account.legacy_code.is_none());
+        let code_path = ParsedCode::path(&Blake2b::from(code.as_bytes()))?;
+        assert_eq!(
+            Storage::get::<String>(hrt, &code_path)?,
+            Some(code.to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn accounts_share_function_code() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+
+        tx.begin();
+
+        let first = PublicKeyHash::digest(b"first")?;
+        let second = PublicKeyHash::digest(b"second")?;
+        let code = ParsedCode::try_from("export default () => {}".to_string()).unwrap();
+
+        Account::create(hrt, tx, &first, 0, Some(code.clone()), None)?;
+        Account::create(hrt, tx, &second, 0, Some(code.clone()), None)?;
+        tx.commit(hrt)?;
+
+        tx.begin();
+        let first_hash = Account::function_code_hash(hrt, tx, &first)?;
+        let second_hash = Account::function_code_hash(hrt, tx, &second)?;
+        assert_eq!(first_hash, Some(code.hash()));
+        assert_eq!(second_hash, Some(code.hash()));
+        assert_eq!(
+            Account::function_code(hrt, tx, &second)?.cloned(),
+            Some(code.to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unreferenced_code_is_removed() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+
+        tx.begin();
+
+        let owner = PublicKeyHash::digest(b"owner")?;
+        let first = PublicKeyHash::digest(b"first")?;
+        let second = PublicKeyHash::digest(b"second")?;
+        let code = ParsedCode::try_from("export default () => {}".to_string()).unwrap();
+        let upgrade = ParsedCode::try_from("export default () => 1".to_string()).unwrap();
+        let code_path = ParsedCode::path(&code.hash())?;
+
+        Account::create(hrt, tx, &first, 0, Some(code.clone()), Some(owner.clone()))?;
+        Account::create(hrt, tx, &second, 0, Some(code.clone()), Some(owner.clone()))?;
+        // Redeploying at an existing address doesn't reference the code
+        let result = Account::create(hrt, tx, &second, 0, Some(code.clone()), None);
+        assert!(matches!(result, Err(Error::InvalidAddress)));
+
+        Account::upgrade_function_code(hrt, tx, &first, &owner, upgrade.clone())?;
+        assert!(tx.contains_key(hrt, &code_path)?);
+
+        Account::upgrade_function_code(hrt, tx, &second, &owner, upgrade)?;
+        assert!(!tx.contains_key(hrt, &code_path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_deploys_to_existing_account_without_code() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+
+        tx.begin();
+
+        let owner = PublicKeyHash::digest(b"owner")?;
+        let address = PublicKeyHash::digest(b"function")?;
+        let code = ParsedCode::try_from("export default () => {}".to_string()).unwrap();
+
+        Account::deposit(hrt, tx, &address, 10)?;
+        Account::create(
+            hrt,
+            tx,
+            &address,
+            5,
+            Some(code.clone()),
+            Some(owner.clone()),
+        )?;
+        assert_eq!(Account::balance(hrt, tx, &address)?, 15);
+        assert_eq!(
+            Account::function_code_hash(hrt, tx, &address)?,
+            Some(code.hash())
+        );
+        assert_eq!(Account::get_mut(hrt, tx, &address)?.owner, Some(owner));
+
+        // Once it has code, the account can't be deployed to again
+        let result = Account::create(hrt, tx, &address, 0, Some(code), None);
+        assert!(matches!(result, Err(Error::InvalidAddress)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn upgrade_removes_metadata() -> Result<()> {
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+
+        tx.begin();
+
+        let owner = PublicKeyHash::digest(b"owner")?;
+        let address = PublicKeyHash::digest(b"function")?;
+        let code = |src: &str| ParsedCode::try_from(src.to_string()).unwrap();
+
+        Account::create(
+            hrt,
+            tx,
+            &address,
+            0,
+            Some(code("export default () => {}")),
+            Some(owner.clone()),
+        )?;
+        let metadata = FunctionMetadata {
+            name: Some("hello".to_string()),
+            ..Default::default()
+        };
+        Account::set_metadata(tx, &address, metadata.clone())?;
+        assert_eq!(Account::metadata(hrt, tx, &address)?, Some(&metadata));
+
+        Account::upgrade_function_code(
+            hrt,
+            tx,
+            &address,
+            &owner,
+            code("export default () => 1"),
+        )?;
+        assert_eq!(Account::metadata(hrt, tx, &address)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn salted_address_vector() -> Result<()> {
+        let deployer = PublicKeyHash::from_base58("tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx")
+            .expect("Could not parse pkh");
+        let code =
+            ParsedCode::try_from("export default () => new Response();".to_string())
+                .unwrap();
+
+        let address = salted_address(&deployer, b"salt", &code.hash())?;
+        assert_eq!(address.to_base58(), "tz1Ww78VLAng6gEd8Szv36tpAxoPNV6tncNv");
+
+        // The address does not depend on the deployer's nonce
+        let hrt = &mut MockHost::default();
+        let tx = &mut Transaction::default();
+        tx.begin();
+        Account::nonce(hrt, tx, &deployer)?.increment();
+        assert_eq!(salted_address(&deployer, b"salt", &code.hash())?, address);
+
+        Ok(())
+    }
 }
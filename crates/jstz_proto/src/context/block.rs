@@ -0,0 +1,46 @@
+use jstz_core::{host::HostRuntime, kv::Storage};
+use tezos_crypto_rs::hash::BlockHash;
+use tezos_smart_rollup::storage::path::RefPath;
+
+use crate::error::Result;
+
+const LEVEL_PATH: RefPath = RefPath::assert_from(b"/jstz_block/level");
+const TIMESTAMP_PATH: RefPath = RefPath::assert_from(b"/jstz_block/timestamp");
+const PREDECESSOR_PATH: RefPath = RefPath::assert_from(b"/jstz_block/predecessor");
+
+pub struct Block;
+
+impl Block {
+    /// Records the level of the inbox currently being processed
+    pub fn set_level(rt: &mut impl HostRuntime, level: u32) -> Result<()> {
+        Ok(Storage::insert(rt, &LEVEL_PATH, &level)?)
+    }
+
+    /// Returns the level of the inbox currently being processed, or 0 if
+    /// no level has been processed yet
+    pub fn level(rt: &impl HostRuntime) -> Result<u32> {
+        Ok(Storage::get::<u32>(rt, &LEVEL_PATH)?.unwrap_or_default())
+    }
+
+    /// Records the timestamp (in seconds since the Unix epoch) and hash of
+    /// the L1 block preceding the inbox currently being processed
+    pub fn set_predecessor(
+        rt: &mut impl HostRuntime,
+        timestamp: i64,
+        predecessor: &BlockHash,
+    ) -> Result<()> {
+        Storage::insert(rt, &TIMESTAMP_PATH, &timestamp)?;
+        Ok(Storage::insert(rt, &PREDECESSOR_PATH, predecessor)?)
+    }
+
+    /// Returns the timestamp (in seconds since the Unix epoch) of the
+    /// predecessor L1 block, or 0 if no level has been processed yet
+    pub fn timestamp(rt: &impl HostRuntime) -> Result<i64> {
+        Ok(Storage::get::<i64>(rt, &TIMESTAMP_PATH)?.unwrap_or_default())
+    }
+
+    /// Returns the hash of the predecessor L1 block, if known
+    pub fn predecessor(rt: &impl HostRuntime) -> Result<Option<BlockHash>> {
+        Ok(Storage::get::<BlockHash>(rt, &PREDECESSOR_PATH)?)
+    }
+}
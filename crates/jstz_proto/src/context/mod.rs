@@ -1,3 +1,5 @@
 pub mod account;
+pub mod block;
+pub mod outbox;
 pub mod receipt;
 pub mod ticket_table;
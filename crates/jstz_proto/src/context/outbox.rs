@@ -0,0 +1,193 @@
+use derive_more::{Display, Error, From};
+use jstz_core::{
+    host::HostRuntime,
+    kv::{Storage, Transaction},
+};
+use serde::{Deserialize, Serialize};
+use tezos_crypto_rs::hash::ContractKt1Hash;
+use tezos_data_encoding::enc::BinWriter;
+use tezos_smart_rollup::{
+    michelson::{
+        ticket::FA2_1Ticket, MichelsonBytes, MichelsonContract, MichelsonNat,
+        MichelsonOption, MichelsonPair,
+    },
+    outbox::{OutboxMessage, OutboxMessageTransaction, OutboxMessageTransactionBatch},
+    storage::path::{OwnedPath, RefPath},
+    types::{Contract, Entrypoint},
+};
+
+use super::account::{Address, Amount};
+use crate::error::Result;
+
+#[derive(Display, Debug, Error, From, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutboxError {
+    OutboxFull,
+    TicketerNotFound,
+    InvalidTicket,
+    InvalidReceiver,
+    EncodingFailed,
+}
+
+/// The maximum number of outbox messages the L1 accepts per level
+const MAX_OUTBOX_MESSAGES_PER_LEVEL: u32 = 100;
+
+const OUTBOX_COUNTER_PATH: RefPath = RefPath::assert_from(b"/jstz_outbox_counter");
+const TICKETER_PATH: RefPath = RefPath::assert_from(b"/ticketer");
+
+const NATIVE_TICKET_ID: u32 = 0_u32;
+const BURN_ENTRYPOINT: &str = "burn";
+
+/// Parameters of the exchanger's `burn` entrypoint: (receiver, tez ticket)
+pub type BurnParameters = MichelsonPair<MichelsonContract, FA2_1Ticket>;
+
+/// Identifies an outbox message on L1 by its outbox level and its index
+/// within that level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxMessageId {
+    pub level: u32,
+    pub index: u32,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct OutboxCounter {
+    level: u32,
+    count: u32,
+}
+
+pub struct Outbox;
+
+impl Outbox {
+    /// Resets the outbox counter at the start of a new inbox level
+    pub fn start_level(rt: &mut impl HostRuntime, level: u32) -> Result<()> {
+        Ok(Storage::insert(
+            rt,
+            &OUTBOX_COUNTER_PATH,
+            &OutboxCounter { level, count: 0 },
+        )?)
+    }
+
+    /// Returns the address of the native ticketer (the exchanger contract)
+    pub fn ticketer(rt: &impl HostRuntime) -> Result<ContractKt1Hash> {
+        Ok(Storage::get::<ContractKt1Hash>(rt, &TICKETER_PATH)?
+            .ok_or(OutboxError::TicketerNotFound)?)
+    }
+
+    fn next_message_id(
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+    ) -> Result<OutboxMessageId> {
+        let counter = tx
+            .entry::<OutboxCounter>(rt, OwnedPath::from(&OUTBOX_COUNTER_PATH))?
+            .or_insert_default();
+
+        if counter.count >= MAX_OUTBOX_MESSAGES_PER_LEVEL {
+            return Err(OutboxError::OutboxFull)?;
+        }
+
+        let id = OutboxMessageId {
+            level: counter.level,
+            index: counter.count,
+        };
+        counter.count += 1;
+
+        Ok(id)
+    }
+
+    /// Queues an outbox message in the transaction, returning the id the
+    /// message will have once the transaction is committed
+    pub fn push(
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+        message: OutboxMessage<BurnParameters>,
+    ) -> Result<OutboxMessageId> {
+        let mut bytes = Vec::new();
+        message
+            .bin_write(&mut bytes)
+            .map_err(|_| OutboxError::EncodingFailed)?;
+
+        let id = Self::next_message_id(rt, tx)?;
+        tx.queue_outbox_message(bytes)?;
+
+        Ok(id)
+    }
+
+    /// Queues an outbox message that burns `amount` native tez tickets with
+    /// the exchanger, sending the tez to `receiver` on L1
+    pub fn push_withdrawal(
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+        receiver: &Address,
+        amount: Amount,
+    ) -> Result<OutboxMessageId> {
+        let ticketer = Self::ticketer(rt)?;
+
+        let receiver = Contract::from_b58check(&receiver.to_base58())
+            .map_err(|_| OutboxError::InvalidReceiver)?;
+
+        let ticket = FA2_1Ticket::new(
+            Contract::Originated(ticketer.clone()),
+            MichelsonPair(
+                MichelsonNat::from(NATIVE_TICKET_ID),
+                MichelsonOption::<MichelsonBytes>(None),
+            ),
+            amount,
+        )
+        .map_err(|_| OutboxError::InvalidTicket)?;
+
+        let transaction = OutboxMessageTransaction {
+            parameters: MichelsonPair(MichelsonContract(receiver), ticket),
+            destination: Contract::Originated(ticketer),
+            entrypoint: Entrypoint::try_from(BURN_ENTRYPOINT.to_string())
+                .expect("`burn` is a valid entrypoint"),
+        };
+
+        let message = OutboxMessage::AtomicTransactionBatch(
+            OutboxMessageTransactionBatch::from(vec![transaction]),
+        );
+
+        Self::push(rt, tx, message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jstz_core::kv::Transaction;
+    use jstz_mock::mock::{self, JstzMockHost};
+
+    use super::{Outbox, OutboxMessageId};
+
+    #[test]
+    fn push_withdrawal_assigns_consecutive_indices() {
+        let mut host = JstzMockHost::default();
+        let mut tx = Transaction::default();
+        let receiver = mock::account1();
+
+        Outbox::start_level(host.rt(), 7).unwrap();
+
+        tx.begin();
+        let first = Outbox::push_withdrawal(host.rt(), &mut tx, &receiver, 10).unwrap();
+        let second = Outbox::push_withdrawal(host.rt(), &mut tx, &receiver, 20).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        assert_eq!(first, OutboxMessageId { level: 7, index: 0 });
+        assert_eq!(second, OutboxMessageId { level: 7, index: 1 });
+    }
+
+    #[test]
+    fn rolled_back_withdrawal_releases_index() {
+        let mut host = JstzMockHost::default();
+        let mut tx = Transaction::default();
+        let receiver = mock::account1();
+
+        Outbox::start_level(host.rt(), 3).unwrap();
+
+        tx.begin();
+        tx.begin();
+        Outbox::push_withdrawal(host.rt(), &mut tx, &receiver, 10).unwrap();
+        tx.rollback().unwrap();
+        let id = Outbox::push_withdrawal(host.rt(), &mut tx, &receiver, 10).unwrap();
+        tx.commit(host.rt()).unwrap();
+
+        assert_eq!(id, OutboxMessageId { level: 3, index: 0 });
+    }
+}
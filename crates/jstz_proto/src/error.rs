@@ -1,7 +1,15 @@
-use boa_engine::{JsError, JsNativeError};
+use std::cell::RefCell;
+
+use boa_engine::{Context, JsError, JsNativeError, JsValue};
 use derive_more::{Display, Error, From};
 
-use crate::context::ticket_table;
+use crate::{
+    context::{
+        account::{Amount, Nonce},
+        outbox, ticket_table,
+    },
+    js_logger::LogRecord,
+};
 
 #[derive(Display, Debug, Error, From)]
 pub enum Error {
@@ -12,47 +20,189 @@ pub enum Error {
         source: jstz_crypto::Error,
     },
     BalanceOverflow,
-    InvalidNonce,
+    #[from(ignore)]
+    #[display(fmt = "InsufficientFunds (balance: {balance}, amount: {amount})")]
+    InsufficientFunds {
+        balance: Amount,
+        amount: Amount,
+    },
+    #[from(ignore)]
+    #[display(fmt = "InvalidNonce (expected: {expected}, actual: {actual})")]
+    InvalidNonce {
+        expected: Nonce,
+        actual: Nonce,
+    },
+    InvalidRollupAddress,
+    OperationExpired,
     InvalidAddress,
+    UnauthorizedUpgrade,
     RefererShouldNotBeSet,
     GasLimitExceeded,
+    #[from(ignore)]
+    #[display(fmt = "{name}: {message}")]
+    JsException {
+        name: String,
+        message: String,
+    },
     InvalidHttpRequest,
+    InvalidPreimage,
+    BatchRunFunctionFailed,
+    AlreadyDeployed,
+    #[from(ignore)]
+    #[display(fmt = "BatchItemFailed (index: {index}): {source}")]
+    BatchItemFailed {
+        index: usize,
+        source: Box<Error>,
+    },
+    InitFailed,
+    /// A smart function failed after running, consuming `gas_used` and
+    /// emitting `logs`
+    #[from(ignore)]
+    #[display(fmt = "RunFunctionFailed (gas used: {gas_used}): {source}")]
+    RunFunctionFailed {
+        source: Box<Error>,
+        gas_used: usize,
+        logs: Vec<LogRecord>,
+    },
     TicketTableError {
         source: ticket_table::TicketTableError,
     },
+    OutboxError {
+        source: outbox::OutboxError,
+    },
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
+thread_local! {
+    /// Host errors thrown into JavaScript, indexed by the `cause` of the
+    /// native error they were converted to
+    static HOST_ERRORS: RefCell<Vec<Option<Error>>> = RefCell::new(Vec::new());
+}
+
 impl From<Error> for JsError {
     fn from(value: Error) -> Self {
         match value {
-            Error::CoreError { source } => source.into(),
-            Error::CryptoError { source } => JsNativeError::eval()
-                .with_message(format!("CryptoError: {}", source))
-                .into(),
+            // Exceptions thrown by JavaScript are propagated as is
+            Error::CoreError {
+                source: jstz_core::Error::JsError { source },
+            } => source,
+            error => {
+                let native = error.to_js_native_error();
+                let index = HOST_ERRORS.with(|errors| {
+                    let mut errors = errors.borrow_mut();
+                    errors.push(Some(error));
+                    errors.len() - 1
+                });
+                native
+                    .with_cause(JsError::from_opaque(JsValue::from(index as u32)))
+                    .into()
+            }
+        }
+    }
+}
+
+impl Error {
+    /// The native error thrown into JavaScript for this error
+    fn to_js_native_error(&self) -> JsNativeError {
+        match self {
+            Error::CoreError { source } => source.to_js_native_error(),
+            Error::CryptoError { source } => {
+                JsNativeError::eval().with_message(format!("CryptoError: {}", source))
+            }
             Error::BalanceOverflow => {
-                JsNativeError::eval().with_message("BalanceOverflow").into()
-            }
-            Error::InvalidNonce => {
-                JsNativeError::eval().with_message("InvalidNonce").into()
-            }
-            Error::InvalidAddress => {
-                JsNativeError::eval().with_message("InvalidAddress").into()
-            }
-            Error::RefererShouldNotBeSet => JsNativeError::eval()
-                .with_message("RefererShouldNotBeSet")
-                .into(),
-            Error::GasLimitExceeded => JsNativeError::eval()
-                .with_message("GasLimitExceeded")
-                .into(),
-            Error::InvalidHttpRequest => JsNativeError::eval()
-                .with_message("InvalidHttpRequest")
-                .into(),
+                JsNativeError::eval().with_message("BalanceOverflow")
+            }
+            err @ Error::InsufficientFunds { .. } => {
+                JsNativeError::eval().with_message(err.to_string())
+            }
+            err @ Error::InvalidNonce { .. } => {
+                JsNativeError::eval().with_message(err.to_string())
+            }
+            Error::InvalidRollupAddress => {
+                JsNativeError::eval().with_message("InvalidRollupAddress")
+            }
+            Error::OperationExpired => {
+                JsNativeError::eval().with_message("OperationExpired")
+            }
+            Error::InvalidAddress => JsNativeError::eval().with_message("InvalidAddress"),
+            Error::UnauthorizedUpgrade => {
+                JsNativeError::eval().with_message("UnauthorizedUpgrade")
+            }
+            Error::RefererShouldNotBeSet => {
+                JsNativeError::eval().with_message("RefererShouldNotBeSet")
+            }
+            Error::GasLimitExceeded => {
+                JsNativeError::eval().with_message("GasLimitExceeded")
+            }
+            err @ Error::JsException { .. } => {
+                JsNativeError::eval().with_message(err.to_string())
+            }
+            Error::InvalidHttpRequest => {
+                JsNativeError::eval().with_message("InvalidHttpRequest")
+            }
+            Error::InvalidPreimage => {
+                JsNativeError::eval().with_message("InvalidPreimage")
+            }
+            Error::BatchRunFunctionFailed => {
+                JsNativeError::eval().with_message("BatchRunFunctionFailed")
+            }
+            Error::AlreadyDeployed => {
+                JsNativeError::eval().with_message("AlreadyDeployed")
+            }
+            err @ Error::BatchItemFailed { .. } => {
+                JsNativeError::eval().with_message(err.to_string())
+            }
+            Error::InitFailed => JsNativeError::eval().with_message("InitFailed"),
+            err @ Error::RunFunctionFailed { .. } => {
+                JsNativeError::eval().with_message(err.to_string())
+            }
             Error::TicketTableError { source } => JsNativeError::eval()
-                .with_message(format!("TicketTableError: {}", source))
-                .into(),
+                .with_message(format!("TicketTableError: {}", source)),
+            Error::OutboxError { source } => {
+                JsNativeError::eval().with_message(format!("OutboxError: {}", source))
+            }
         }
     }
+
+    /// Recovers the error of an uncaught JavaScript exception. Host errors
+    /// thrown into JavaScript are recovered as they were raised; anything
+    /// else is captured by the name and message of the exception.
+    pub fn from_js_error(err: JsError, context: &mut Context<'_>) -> Self {
+        let error = match err.try_native(context) {
+            Ok(native) => {
+                take_host_error(&native).unwrap_or_else(|| Error::JsException {
+                    name: native.kind.to_string(),
+                    message: native.message().to_string(),
+                })
+            }
+            Err(_) => Error::JsException {
+                name: "Error".to_string(),
+                message: err.to_string(),
+            },
+        };
+        clear_host_errors();
+        error
+    }
+}
+
+/// Takes the host error `native` was converted from, unless the exception was
+/// thrown by JavaScript
+fn take_host_error(native: &JsNativeError) -> Option<Error> {
+    let index = native.cause()?.as_opaque()?.as_number()?;
+    HOST_ERRORS.with(|errors| {
+        let mut errors = errors.borrow_mut();
+        let error = errors.get_mut(index as usize)?;
+        let raised = error.as_ref()?.to_js_native_error();
+        if raised.message() != native.message() {
+            return None;
+        }
+        error.take()
+    })
+}
+
+/// Forgets the host errors thrown into JavaScript so far
+pub(crate) fn clear_host_errors() {
+    HOST_ERRORS.with(|errors| errors.borrow_mut().clear());
 }
 
 impl From<boa_engine::JsNativeError> for Error {
@@ -1,13 +1,35 @@
 use jstz_core::{host::HostRuntime, kv::Transaction};
 
-use crate::{context::account::Account, operation::external::Deposit, Result};
+use crate::{
+    context::account::Account,
+    operation::external::Deposit,
+    receipt::{self, Receipt},
+    Result,
+};
 
-pub fn execute(
+fn execute_inner(
     hrt: &mut impl HostRuntime,
     tx: &mut Transaction,
     deposit: Deposit,
-) -> Result<()> {
-    let Deposit { amount, reciever } = deposit;
+) -> Result<receipt::Content> {
+    let Deposit {
+        amount, reciever, ..
+    } = deposit;
+
+    Account::deposit(hrt, tx, &reciever, amount)?;
+
+    Ok(receipt::Content::Deposit(receipt::Deposit {
+        receiver: reciever,
+        amount,
+    }))
+}
 
-    Account::deposit(hrt, tx, &reciever, amount)
+pub fn execute(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    deposit: Deposit,
+) -> Receipt {
+    let hash = deposit.hash();
+    let inner = execute_inner(hrt, tx, deposit);
+    Receipt::new(hash, inner)
 }
@@ -0,0 +1,39 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::ticket_table::TicketTable,
+    operation::external::FaDeposit,
+    receipt::{self, Receipt},
+    Result,
+};
+
+fn execute_inner(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    deposit: FaDeposit,
+) -> Result<receipt::Content> {
+    let FaDeposit {
+        amount,
+        receiver,
+        ticket_hash,
+        ..
+    } = deposit;
+
+    TicketTable::add(hrt, tx, &receiver, &ticket_hash, amount)?;
+
+    Ok(receipt::Content::FaDeposit(receipt::FaDeposit {
+        receiver,
+        ticket_hash,
+        amount,
+    }))
+}
+
+pub fn execute(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    deposit: FaDeposit,
+) -> Receipt {
+    let hash = deposit.hash();
+    let inner = execute_inner(hrt, tx, deposit);
+    Receipt::new(hash, inner)
+}
@@ -1,55 +1,167 @@
 use jstz_core::{host::HostRuntime, kv::Transaction};
 
 use crate::{
-    operation::{self, ExternalOperation, Operation, SignedOperation},
+    context::account::Address,
+    operation::{self, ExternalOperation, Operation, OperationHash, SignedOperation},
     receipt::{self, Receipt},
-    Result,
+    Error, Result,
 };
 
 pub mod deposit;
+pub mod fa_deposit;
 pub mod smart_function;
+pub mod transfer;
+pub mod withdraw;
 
-fn execute_operation_inner(
+fn execute_content(
     hrt: &mut impl HostRuntime,
     tx: &mut Transaction,
-    signed_operation: SignedOperation,
+    source: &Address,
+    content: operation::Content,
+    operation_hash: &OperationHash,
 ) -> Result<receipt::Content> {
-    let operation = signed_operation.verify()?;
-    let operation_hash = operation.hash();
+    match content {
+        operation::Content::DeployFunction(deployment) => {
+            let result = smart_function::deploy::execute(
+                hrt,
+                tx,
+                source,
+                deployment,
+                operation_hash,
+            )?;
 
-    operation.verify_nonce(hrt, tx)?;
+            Ok(receipt::Content::DeployFunction(result))
+        }
 
-    match operation {
-        Operation {
-            source,
-            content: operation::Content::DeployFunction(deployment),
-            ..
-        } => {
-            let result = smart_function::deploy::execute(hrt, tx, &source, deployment)?;
+        operation::Content::RevealDeployFunction(deployment) => {
+            let result = smart_function::deploy::execute_reveal(
+                hrt,
+                tx,
+                source,
+                deployment,
+                operation_hash,
+            )?;
 
             Ok(receipt::Content::DeployFunction(result))
         }
 
-        Operation {
-            content: operation::Content::RunFunction(run),
-            source,
-            ..
-        } => {
-            let result =
-                smart_function::run::execute(hrt, tx, &source, run, operation_hash)?;
+        operation::Content::RunFunction(run) => {
+            let result = smart_function::run::execute(
+                hrt,
+                tx,
+                source,
+                run,
+                operation_hash.clone(),
+            )?;
 
             Ok(receipt::Content::RunFunction(result))
         }
+
+        operation::Content::UpgradeFunction(upgrade) => {
+            let result = smart_function::upgrade::execute(hrt, tx, source, upgrade)?;
+
+            Ok(receipt::Content::UpgradeFunction(result))
+        }
+
+        operation::Content::Withdraw(withdrawal) => {
+            let result = withdraw::execute(hrt, tx, source, withdrawal)?;
+
+            Ok(receipt::Content::Withdraw(result))
+        }
+
+        operation::Content::Transfer(transfer) => {
+            let result = transfer::execute(hrt, tx, source, transfer)?;
+
+            Ok(receipt::Content::Transfer(result))
+        }
+
+        operation::Content::Batch(contents) => {
+            tx.begin();
+            match execute_batch(hrt, tx, source, contents, operation_hash) {
+                Ok(receipts) => {
+                    tx.commit(hrt)?;
+                    Ok(receipt::Content::Batch(receipts))
+                }
+                Err(err) => {
+                    tx.rollback()?;
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+/// Executes the contents of a batch in order, failing on the first content
+/// that fails or whose smart function responds with a non-2xx status. The
+/// error records the index of the failing content.
+fn execute_batch(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    contents: Vec<operation::BatchItem>,
+    operation_hash: &OperationHash,
+) -> Result<Vec<receipt::Content>> {
+    let mut receipts = Vec::with_capacity(contents.len());
+
+    for (index, content) in contents.into_iter().enumerate() {
+        let receipt = execute_batch_item(hrt, tx, source, content, operation_hash)
+            .map_err(|err| Error::BatchItemFailed {
+                index,
+                source: Box::new(err),
+            })?;
+
+        receipts.push(receipt);
+    }
+
+    Ok(receipts)
+}
+
+fn execute_batch_item(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    content: operation::BatchItem,
+    operation_hash: &OperationHash,
+) -> Result<receipt::Content> {
+    let receipt = execute_content(hrt, tx, source, content.into(), operation_hash)?;
+
+    if let receipt::Content::RunFunction(run) = &receipt {
+        if !run.status_code.is_success() {
+            return Err(Error::BatchRunFunctionFailed);
+        }
     }
+
+    Ok(receipt)
+}
+
+fn execute_operation_inner(
+    hrt: &mut impl HostRuntime,
+    tx: &mut Transaction,
+    signed_operation: SignedOperation,
+) -> Result<receipt::Content> {
+    let operation = signed_operation.verify()?;
+    let operation_hash = operation.hash();
+
+    operation.verify_scope(hrt)?;
+    operation.verify_nonce(hrt, tx)?;
+
+    let Operation {
+        source, content, ..
+    } = operation;
+
+    execute_content(hrt, tx, &source, content, &operation_hash)
 }
 
 pub fn execute_external_operation(
     hrt: &mut impl HostRuntime,
     tx: &mut Transaction,
     external_operation: ExternalOperation,
-) -> Result<()> {
+) -> Receipt {
     match external_operation {
         ExternalOperation::Deposit(deposit) => deposit::execute(hrt, tx, deposit),
+        ExternalOperation::FaDeposit(fa_deposit) => {
+            fa_deposit::execute(hrt, tx, fa_deposit)
+        }
     }
 }
 
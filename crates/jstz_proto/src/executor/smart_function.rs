@@ -1,9 +1,15 @@
-use std::{io::Read, ops::BitXor};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    io::Read,
+    ops::BitXor,
+    rc::Rc,
+};
 
 use boa_engine::{
-    js_string,
     object::{builtins::JsPromise, FunctionObjectBuilder},
-    Context, JsArgs, JsError, JsNativeError, JsResult, JsValue, NativeFunction, Source,
+    Context, JsArgs, JsError, JsNativeError, JsResult, JsString, JsValue, NativeFunction,
+    Source,
 };
 use boa_gc::{Finalize, Trace};
 use derive_more::{Deref, DerefMut};
@@ -19,17 +25,22 @@ use jstz_core::{
     host::HostRuntime, host_defined, kv::Transaction, native::JsNativeObject, runtime,
     Module, Realm,
 };
+use jstz_crypto::hash::Blake2b;
 use tezos_smart_rollup::prelude::debug_msg;
 
 use crate::{
     api::{self, TraceData},
-    context::account::{Account, Address, Amount, ParsedCode},
+    context::{
+        account::{salted_address, Account, Address, Amount, ParsedCode},
+        block::Block,
+    },
+    error,
     operation::OperationHash,
     request_logger::{log_request_end, log_request_start},
     Error, Result,
 };
 
-use crate::js_logger::JsonLogger;
+use crate::js_logger::{self, JsonLogger};
 
 pub mod headers {
 
@@ -142,50 +153,138 @@ pub fn register_jstz_apis(
         },
         context,
     );
+    realm.register_api(
+        api::TicketsApi {
+            address: address.clone(),
+        },
+        context,
+    );
     realm.register_api(
         api::SmartFunctionApi {
             address: address.clone(),
         },
         context,
     );
+    realm.register_api(api::BlockApi, context);
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Deref, DerefMut, Trace, Finalize)]
 pub struct Script(Module);
 
+/// Gas charged per byte of source code parsed. Parsing isn't metered by the
+/// interpreter, so it is accounted for separately and added to the gas used.
+pub const PARSE_GAS_PER_BYTE: usize = 1;
+
+/// Caches used when loading scripts.
+///
+/// Code is stored under its hash, so the source of a given code hash never
+/// changes: sources are kept (up to `MAX_SOURCE_BYTES`) for as long as the
+/// kernel runs, across the operations of a level. Parsing a code hash is only
+/// charged the first time it is parsed during an operation. Each call still
+/// parses and evaluates a fresh module, since a module can neither be evaluated
+/// twice nor moved to the realm of another call.
+mod script_cache {
+    use super::*;
+
+    /// Maximum number of source bytes kept across operations
+    const MAX_SOURCE_BYTES: usize = 4 * 1024 * 1024;
+
+    thread_local! {
+        static SOURCES: RefCell<(BTreeMap<Blake2b, Rc<str>>, usize)> =
+            RefCell::new((BTreeMap::new(), 0));
+        /// The code hashes parsed during the operation
+        static PARSED: RefCell<(Option<OperationHash>, BTreeSet<Blake2b>)> =
+            RefCell::new((None, BTreeSet::new()));
+        static PARSE_GAS: Cell<usize> = Cell::new(0);
+    }
+
+    pub fn source(code_hash: &Blake2b) -> Option<Rc<str>> {
+        SOURCES.with(|sources| sources.borrow().0.get(code_hash).cloned())
+    }
+
+    pub fn insert_source(code_hash: Blake2b, source: Rc<str>) {
+        SOURCES.with(|sources| {
+            let (sources, bytes) = &mut *sources.borrow_mut();
+            if *bytes + source.len() <= MAX_SOURCE_BYTES {
+                *bytes += source.len();
+                sources.insert(code_hash, source);
+            }
+        })
+    }
+
+    /// Charges for parsing `bytes` of code, unless `code_hash` was already
+    /// parsed during the operation
+    pub fn charge_parse(
+        operation_hash: &OperationHash,
+        code_hash: &Blake2b,
+        bytes: usize,
+    ) {
+        let is_miss = PARSED.with(|parsed| {
+            let (parsed_operation, code_hashes) = &mut *parsed.borrow_mut();
+            if parsed_operation.as_ref() != Some(operation_hash) {
+                *parsed_operation = Some(operation_hash.clone());
+                code_hashes.clear();
+            }
+            code_hashes.insert(code_hash.clone())
+        });
+        if is_miss {
+            PARSE_GAS.with(|gas| gas.set(gas.get() + bytes * PARSE_GAS_PER_BYTE));
+        }
+    }
+
+    /// Returns the gas charged for parsing since it was last taken
+    pub fn take_parse_gas() -> usize {
+        PARSE_GAS.with(|gas| gas.replace(0))
+    }
+}
+
 impl Script {
-    fn get_default_export(&self, context: &mut Context<'_>) -> JsResult<JsValue> {
-        self.namespace(context).get(js_string!("default"), context)
+    fn get_export(&self, name: &str, context: &mut Context<'_>) -> JsResult<JsValue> {
+        self.namespace(context).get(JsString::from(name), context)
     }
 
     fn invoke_handler(
         &self,
+        export: &str,
         this: &JsValue,
         args: &[JsValue],
         context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
-        let default_export = self.get_default_export(context)?;
+        let handler_export = self.get_export(export, context)?;
 
-        let handler = default_export.as_object().ok_or_else(|| {
-            JsError::from_native(
-                JsNativeError::typ()
-                    .with_message("Failed to convert `default` export to js object"),
-            )
+        let handler = handler_export.as_object().ok_or_else(|| {
+            JsError::from_native(JsNativeError::typ().with_message(format!(
+                "Failed to convert `{export}` export to js object"
+            )))
         })?;
 
         handler.call(this, args, context)
     }
 
+    /// Loads the script of the smart function at `address`, whose code hash is
+    /// `code_hash`, charging the gas for parsing it
     pub fn load(
         hrt: &mut impl HostRuntime,
         tx: &mut Transaction,
         address: &Address,
+        code_hash: &Blake2b,
+        operation_hash: &OperationHash,
         context: &mut Context<'_>,
     ) -> Result<Self> {
-        let src =
-            Account::function_code(hrt, tx, address)?.ok_or(Error::InvalidAddress)?;
+        let src = match script_cache::source(code_hash) {
+            Some(src) => src,
+            None => {
+                let src: Rc<str> = Account::function_code(hrt, tx, address)?
+                    .ok_or(Error::InvalidAddress)?
+                    .as_str()
+                    .into();
+                script_cache::insert_source(code_hash.clone(), src.clone());
+                src
+            }
+        };
+        script_cache::charge_parse(operation_hash, code_hash, src.len());
 
-        Ok(Self::parse(Source::from_bytes(&src), context)?)
+        Ok(Self::parse(Source::from_bytes(src.as_bytes()), context)?)
     }
 
     pub fn parse<R: Read>(
@@ -224,19 +323,29 @@ impl Script {
         self.realm().eval_module(self, context)
     }
 
-    /// Deploys a script
+    /// Deploys a script, owned by `owner`. Its address is derived from the salt
+    /// if one is given, otherwise from the nonce of the source.
+    ///
+    /// Returns the address of the script and whether it was newly deployed
+    /// (rather than already deployed at that address)
     pub fn deploy(
         hrt: &impl HostRuntime,
         tx: &mut Transaction,
         source: &Address,
         code: ParsedCode,
         balance: Amount,
-    ) -> Result<Address> {
-        let nonce = Account::nonce(hrt, tx, source)?;
-
-        let address = Address::digest(format!("{}{}{}", source, code, nonce).as_bytes())?;
+        salt: Option<&[u8]>,
+        owner: Option<Address>,
+    ) -> Result<(Address, bool)> {
+        let address = match salt {
+            Some(salt) => salted_address(source, salt, &code.hash())?,
+            None => {
+                let nonce = Account::nonce(hrt, tx, source)?;
+                Address::digest(format!("{}{}{}", source, code, nonce).as_bytes())?
+            }
+        };
 
-        let account = Account::create(hrt, tx, &address, balance, Some(code));
+        let account = Account::create(hrt, tx, &address, balance, Some(code), owner);
         if account.is_ok() {
             debug_msg!(hrt, "[📜] Smart function deployed: {address}\n");
         } else if let Err(Error::InvalidAddress) = account {
@@ -247,7 +356,7 @@ impl Script {
             account?
         }
 
-        Ok(address)
+        Ok((address, account.is_ok()))
     }
 
     /// Runs the script
@@ -257,6 +366,18 @@ impl Script {
         operation_hash: &OperationHash,
         request: &JsValue,
         context: &mut Context<'_>,
+    ) -> JsResult<JsValue> {
+        self.run_export("default", address, operation_hash, request, context)
+    }
+
+    /// Runs the handler exported by the script under the name `export`
+    fn run_export(
+        &self,
+        export: &str,
+        address: &Address,
+        operation_hash: &OperationHash,
+        request: &JsValue,
+        context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
         let context = &mut self.realm().context_handle(context);
 
@@ -281,8 +402,12 @@ impl Script {
         log_request_start(address.clone(), operation_hash.to_string());
 
         // 4. Invoke the script's handler
-        let result =
-            self.invoke_handler(&JsValue::undefined(), &[request.clone()], context);
+        let result = self.invoke_handler(
+            export,
+            &JsValue::undefined(),
+            &[request.clone()],
+            context,
+        );
 
         // TODO: decode request and add more fields to the request (status, header etc).
         log_request_end(address.clone(), operation_hash.to_string());
@@ -309,6 +434,22 @@ impl Script {
         )
     }
 
+    /// Loads and initializes the script of the smart function at `address`
+    fn load_init(
+        address: &Address,
+        operation_hash: &OperationHash,
+        context: &mut Context<'_>,
+    ) -> JsResult<(Self, JsPromise)> {
+        let script = runtime::with_js_hrt_and_tx(|hrt, tx| {
+            let code_hash = Account::function_code_hash(hrt, tx, address)?
+                .ok_or(Error::InvalidAddress)?;
+            Script::load(hrt, tx, address, &code_hash, operation_hash, context)
+        })?;
+        let script_promise = script.init(address, operation_hash, context)?;
+
+        Ok((script, script_promise))
+    }
+
     /// Loads, initializes and runs the script
     pub fn load_init_run(
         address: Address,
@@ -316,24 +457,17 @@ impl Script {
         request: &JsValue,
         context: &mut Context<'_>,
     ) -> JsResult<JsValue> {
-        // 1. Load script
-
-        let script = runtime::with_js_hrt_and_tx(|hrt, tx| {
-            Script::load(hrt, tx, &address, context)
-        })?;
-
-        // 2. Evaluate the script's module
-        let script_promise = script.init(&address, &operation_hash, context)?;
+        // 1. Load script and evaluate its module
+        let (script, script_promise) =
+            Self::load_init(&address, &operation_hash, context)?;
 
-        // 3. Once evaluated, call the script's handler
+        // 2. Once evaluated, call the script's handler
         let result = script_promise.then(
             Some(
                 FunctionObjectBuilder::new(context.realm(), unsafe {
                     NativeFunction::from_closure_with_captures(
                         |_, _, (address, operation_hash, script, request), context| {
-                            {
-                                script.run(address, operation_hash, request, context)
-                            }
+                            script.run(address, operation_hash, request, context)
                         },
                         (address, operation_hash, script, request.clone()),
                     )
@@ -385,6 +519,8 @@ pub mod run {
         } = run;
 
         // 1. Initialize runtime (with Web APIs to construct request)
+        // `Date` observes the timestamp of the predecessor L1 block
+        runtime::set_utc_now(Block::timestamp(hrt)?);
         let rt = &mut jstz_core::Runtime::new(gas_limit)?;
         register_web_apis(&rt.realm().clone(), rt);
 
@@ -403,8 +539,16 @@ pub mod run {
         headers::test_and_set_referrer(&request.deref(), source)?;
 
         // 5. Run :)
-        let result: JsValue = {
+        // The run's writes are buffered in a dedicated snapshot so they can be
+        // measured before being committed
+        tx.begin();
+        js_logger::take_log_records();
+        error::clear_host_errors();
+
+        let result: Result<JsValue> = {
             let rt = &mut *rt;
+            let address = address.clone();
+            let operation_hash = operation_hash.clone();
             runtime::enter_js_host_context(hrt, tx, || {
                 jstz_core::future::block_on(async move {
                     let result = Script::load_init_run(
@@ -422,13 +566,46 @@ pub mod run {
             if rt.instructions_remaining() == 0 {
                 Error::GasLimitExceeded
             } else {
-                err.into()
+                Error::from_js_error(err, rt)
             }
-        })?;
+        });
+        let parse_gas = script_cache::take_parse_gas();
+        let logs = js_logger::take_log_records();
+
+        // Parsing is charged on top of the instructions executed
+        let gas_used = gas_limit - rt.instructions_remaining() + parse_gas;
+        let result = result.and_then(|result| {
+            if gas_used > gas_limit {
+                Err(Error::GasLimitExceeded)
+            } else {
+                Ok(result)
+            }
+        });
+
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                js_logger::log_uncaught_exception(
+                    hrt,
+                    address,
+                    operation_hash.to_string(),
+                    &err,
+                );
+                tx.rollback()?;
+                return Err(Error::RunFunctionFailed {
+                    source: Box::new(err),
+                    gas_used,
+                    logs,
+                });
+            }
+        };
+
+        let writes = tx.current_writes()?;
+        tx.commit(hrt)?;
 
         debug_msg!(
             hrt,
-            "🚀 Smart function executed successfully with value: {:?} (in {:?} instructions)\n", result, gas_limit - rt.instructions_remaining()
+            "🚀 Smart function executed successfully with value: {:?} (in {:?} instructions)\n", result, gas_used
         );
 
         // 6. Serialize response
@@ -440,27 +617,242 @@ pub mod run {
             body,
             status_code: http_parts.status,
             headers: http_parts.headers,
+            gas_used,
+            kv_writes: writes.count,
+            kv_write_bytes: writes.bytes,
+            logs,
         })
     }
 }
 
 pub mod deploy {
     use super::*;
-    use crate::{operation, receipt};
+    use crate::{operation, preimage, receipt};
+
+    /// The export called when a smart function is deployed
+    pub const INIT_EXPORT: &str = "init";
+
+    /// The gas available to evaluate the module of a newly deployed smart
+    /// function and run its `init` export, unless the deployment sets a gas
+    /// limit
+    pub const DEFAULT_INIT_GAS_LIMIT: usize = 550_000;
+
+    /// Evaluates the module of a newly deployed smart function and, if it
+    /// exports `init`, calls it with a request whose referer is the deployer.
+    /// Fails if `init` throws or responds with a non-2xx status.
+    fn init(
+        hrt: &mut impl HostRuntime,
+        tx: &mut Transaction,
+        deployer: &Address,
+        address: &Address,
+        operation_hash: &OperationHash,
+        gas_limit: usize,
+    ) -> Result<()> {
+        runtime::set_utc_now(Block::timestamp(hrt)?);
+        let rt = &mut jstz_core::Runtime::new(gas_limit)?;
+        register_web_apis(&rt.realm().clone(), rt);
+
+        let http_request = http::Request::builder()
+            .uri(format!("tezos://{}/", address))
+            .method(http::Method::POST)
+            .body(None)
+            .map_err(|_| Error::InvalidHttpRequest)?;
+        let request = JsNativeObject::new::<RequestClass>(
+            Request::from_http_request(http_request, rt)?,
+            rt,
+        )?;
+        headers::test_and_set_referrer(&request.deref(), deployer)?;
+
+        js_logger::take_log_records();
+        error::clear_host_errors();
+        let result: Result<Option<JsValue>> = {
+            let rt = &mut *rt;
+            runtime::enter_js_host_context(hrt, tx, || {
+                jstz_core::future::block_on(async move {
+                    let (script, script_promise) =
+                        Script::load_init(address, operation_hash, rt)?;
+                    rt.resolve_value(&script_promise.into()).await?;
 
+                    if script.get_export(INIT_EXPORT, rt)?.is_undefined() {
+                        return Ok(None);
+                    }
+
+                    let result = script.run_export(
+                        INIT_EXPORT,
+                        address,
+                        operation_hash,
+                        request.inner(),
+                        rt,
+                    )?;
+
+                    Ok(Some(rt.resolve_value(&result).await?))
+                })
+            })
+        }
+        .map_err(|err| {
+            if rt.instructions_remaining() == 0 {
+                Error::GasLimitExceeded
+            } else {
+                Error::from_js_error(err, rt)
+            }
+        });
+        let parse_gas = script_cache::take_parse_gas();
+        js_logger::take_log_records();
+
+        // Parsing is charged on top of the instructions executed
+        let result = result.and_then(|result| {
+            if parse_gas > rt.instructions_remaining() {
+                Err(Error::GasLimitExceeded)
+            } else {
+                Ok(result)
+            }
+        });
+
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                js_logger::log_uncaught_exception(
+                    hrt,
+                    address.clone(),
+                    operation_hash.to_string(),
+                    &err,
+                );
+                return Err(err);
+            }
+        };
+
+        match result {
+            Some(response) if !Response::try_from_js(&response)?.ok() => {
+                Err(Error::InitFailed)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Deploys a smart function, recording its metadata and running its `init`
+    /// export (if any) within the same transaction, with the gas limit of the
+    /// deployment or `DEFAULT_INIT_GAS_LIMIT`. The deployment fails if a smart
+    /// function is already deployed at its address, and is aborted if `init`
+    /// fails.
     pub fn execute(
-        hrt: &impl HostRuntime,
+        hrt: &mut impl HostRuntime,
         tx: &mut Transaction,
         source: &Address,
         deployment: operation::DeployFunction,
+        operation_hash: &OperationHash,
     ) -> Result<receipt::DeployFunction> {
         let operation::DeployFunction {
             function_code,
             account_credit,
+            salt,
+            metadata,
+            gas_limit,
+            owner,
         } = deployment;
 
-        let address = Script::deploy(hrt, tx, source, function_code, account_credit)?;
+        tx.begin();
+        let result = Script::deploy(
+            hrt,
+            tx,
+            source,
+            function_code,
+            account_credit,
+            salt.as_deref(),
+            owner,
+        )
+        .and_then(|(address, deployed)| {
+            if !deployed {
+                return Err(Error::AlreadyDeployed);
+            }
+            if let Some(metadata) = metadata {
+                Account::set_metadata(tx, &address, metadata)?;
+            }
+            let gas_limit = gas_limit.unwrap_or(DEFAULT_INIT_GAS_LIMIT);
+            init(hrt, tx, source, &address, operation_hash, gas_limit)?;
+            Ok(address)
+        });
+
+        match result {
+            Ok(address) => {
+                tx.commit(hrt)?;
+                Ok(receipt::DeployFunction { address })
+            }
+            Err(err) => {
+                tx.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Reveals the code of a large smart function from its preimages and
+    /// deploys it. The gas limit (or `DEFAULT_INIT_GAS_LIMIT`) pays for
+    /// revealing the pages, and what is left of it for running `init`.
+    pub fn execute_reveal(
+        hrt: &mut impl HostRuntime,
+        tx: &mut Transaction,
+        source: &Address,
+        deployment: operation::RevealDeployFunction,
+        operation_hash: &OperationHash,
+    ) -> Result<receipt::DeployFunction> {
+        let operation::RevealDeployFunction {
+            root_hash,
+            account_credit,
+            salt,
+            metadata,
+            gas_limit,
+            owner,
+        } = deployment;
+
+        let root_hash = root_hash
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::InvalidPreimage)?;
+        // Revealing is paid for out of the gas limit, leaving the rest to `init`
+        let gas_limit = gas_limit.unwrap_or(DEFAULT_INIT_GAS_LIMIT);
+        let (code, reveal_gas) = preimage::reveal(hrt, root_hash, gas_limit)?;
+        let code = String::from_utf8(code).map_err(|_| Error::InvalidPreimage)?;
+        let function_code = ParsedCode::try_from(code)?;
+        debug_msg!(hrt, "[📜] Smart function code revealed from preimages\n");
+
+        execute(
+            hrt,
+            tx,
+            source,
+            operation::DeployFunction {
+                function_code,
+                account_credit,
+                salt,
+                metadata,
+                gas_limit: Some(gas_limit - reveal_gas),
+                owner,
+            },
+            operation_hash,
+        )
+    }
+}
+
+pub mod upgrade {
+    use super::*;
+    use crate::{operation, receipt};
+
+    pub fn execute(
+        hrt: &impl HostRuntime,
+        tx: &mut Transaction,
+        source: &Address,
+        upgrade: operation::UpgradeFunction,
+    ) -> Result<receipt::UpgradeFunction> {
+        let operation::UpgradeFunction {
+            address,
+            function_code,
+        } = upgrade;
+
+        let previous_code_hash =
+            Account::upgrade_function_code(hrt, tx, &address, source, function_code)?;
+        debug_msg!(hrt, "[📜] Smart function upgraded: {address}\n");
 
-        Ok(receipt::DeployFunction { address })
+        Ok(receipt::UpgradeFunction {
+            address,
+            previous_code_hash,
+        })
     }
 }
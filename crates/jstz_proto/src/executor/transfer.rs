@@ -0,0 +1,20 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::account::{Account, Address},
+    operation::Transfer,
+    receipt, Result,
+};
+
+pub fn execute(
+    hrt: &impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    transfer: Transfer,
+) -> Result<receipt::Transfer> {
+    let Transfer { amount, receiver } = transfer;
+
+    Account::transfer(hrt, tx, source, &receiver, amount)?;
+
+    Ok(receipt::Transfer { receiver, amount })
+}
@@ -0,0 +1,24 @@
+use jstz_core::{host::HostRuntime, kv::Transaction};
+
+use crate::{
+    context::{
+        account::{Account, Address},
+        outbox::Outbox,
+    },
+    operation::Withdraw,
+    receipt, Result,
+};
+
+pub fn execute(
+    hrt: &impl HostRuntime,
+    tx: &mut Transaction,
+    source: &Address,
+    withdrawal: Withdraw,
+) -> Result<receipt::Withdraw> {
+    let Withdraw { amount, receiver } = withdrawal;
+
+    Account::withdraw(hrt, tx, source, amount)?;
+    let outbox_message_id = Outbox::push_withdrawal(hrt, tx, &receiver, amount)?;
+
+    Ok(receipt::Withdraw { outbox_message_id })
+}
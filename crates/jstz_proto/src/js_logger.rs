@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{cell::RefCell, fmt::Display, mem};
 
 use boa_engine::prelude::Context;
 pub use jstz_api::js_log::{JsLog, LogData, LogLevel};
@@ -8,10 +8,11 @@ use serde::Serialize;
 
 use crate::api::TraceData;
 use crate::context::account::Address;
+use crate::Error;
 
 pub const LOG_PREFIX: &str = "[JSTZ:SMART_FUNCTION:LOG] ";
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LogRecord {
     pub address: Address,
     pub request_id: String,
@@ -54,13 +55,128 @@ impl LogRecord {
     }
 }
 
+/// The maximum number of log records, and the maximum total length of their
+/// text, kept for a receipt. Further records are only written to the debug log.
+const MAX_LOG_RECORDS: usize = 100;
+const MAX_LOG_BYTES: usize = 8 * 1024;
+
+#[derive(Default)]
+struct LogRecords {
+    records: Vec<LogRecord>,
+    bytes: usize,
+    truncated: usize,
+}
+
+impl LogRecords {
+    fn push(&mut self, record: LogRecord) {
+        if self.truncated == 0
+            && self.records.len() < MAX_LOG_RECORDS
+            && self.bytes + record.text.len() <= MAX_LOG_BYTES
+        {
+            self.bytes += record.text.len();
+            self.records.push(record);
+            return;
+        }
+
+        // The first dropped record is replaced by a marker, whose text is set
+        // once the number of dropped records is known
+        if self.truncated == 0 {
+            self.records.push(LogRecord {
+                level: LogLevel::WARN,
+                text: String::new(),
+                ..record
+            });
+        }
+        self.truncated += 1;
+    }
+
+    fn take(&mut self) -> Vec<LogRecord> {
+        let LogRecords {
+            mut records,
+            truncated,
+            ..
+        } = mem::take(self);
+
+        if truncated > 0 {
+            if let Some(marker) = records.last_mut() {
+                marker.text = format!("[{truncated} log records truncated]");
+            }
+        }
+        records
+    }
+}
+
+thread_local! {
+    /// Log records emitted since the last call to `take_log_records`
+    static LOG_RECORDS: RefCell<LogRecords> = RefCell::new(LogRecords::default());
+}
+
+/// Takes the log records emitted by smart functions since the last call,
+/// truncated to at most [`MAX_LOG_RECORDS`] records of [`MAX_LOG_BYTES`]
+pub(crate) fn take_log_records() -> Vec<LogRecord> {
+    LOG_RECORDS.with(|records| records.borrow_mut().take())
+}
+
+/// Logs an exception that a smart function failed to catch
+pub(crate) fn log_uncaught_exception(
+    hrt: &impl HostRuntime,
+    address: Address,
+    request_id: String,
+    error: &Error,
+) {
+    let log_record = LogRecord {
+        address,
+        request_id,
+        level: LogLevel::ERROR,
+        text: format!("Uncaught {error}"),
+    };
+    hrt.write_debug(&(LOG_PREFIX.to_string() + &log_record.to_string() + "\n"));
+}
+
 pub(crate) struct JsonLogger;
 
 impl JsLog for JsonLogger {
     fn log(&self, log_data: LogData, context: &mut Context<'_>) {
-        let log_record = LogRecord::new(log_data, context).to_string();
+        let log_record = LogRecord::new(log_data, context);
         runtime::with_js_hrt(|hrt| {
-            hrt.write_debug(&(LOG_PREFIX.to_string() + &log_record + "\n"));
+            hrt.write_debug(&(LOG_PREFIX.to_string() + &log_record.to_string() + "\n"));
         });
+        LOG_RECORDS.with(|records| records.borrow_mut().push(log_record));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jstz_crypto::public_key_hash::PublicKeyHash;
+
+    use super::*;
+
+    fn record(text: &str) -> LogRecord {
+        LogRecord {
+            address: PublicKeyHash::from_base58("tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx")
+                .unwrap(),
+            request_id: "request".to_string(),
+            level: LogLevel::LOG,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn log_records_are_truncated() {
+        let mut records = LogRecords::default();
+        for _ in 0..MAX_LOG_RECORDS + 10 {
+            records.push(record("log"));
+        }
+
+        let records = records.take();
+        assert_eq!(records.len(), MAX_LOG_RECORDS + 1);
+        assert_eq!(records[MAX_LOG_RECORDS].text, "[10 log records truncated]");
+
+        let mut records = LogRecords::default();
+        records.push(record(&"a".repeat(MAX_LOG_BYTES)));
+        records.push(record("b"));
+        let records = records.take();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].text, "[1 log records truncated]");
     }
 }
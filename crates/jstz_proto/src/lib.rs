@@ -5,6 +5,7 @@ pub mod context;
 pub mod executor;
 pub mod js_logger;
 pub mod operation;
+pub mod preimage;
 pub mod receipt;
 pub mod request_logger;
 
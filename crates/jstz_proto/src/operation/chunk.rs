@@ -0,0 +1,126 @@
+use jstz_crypto::{hash::Blake2b, public_key::PublicKey, signature::Signature};
+use serde::{Deserialize, Serialize};
+
+use super::{OperationHash, SignedOperation};
+use crate::Result;
+
+/// The maximum number of operation bytes carried by a single chunk. An
+/// external inbox message is limited to 4096 bytes, which must also fit the
+/// message frame and the chunk's own encoding.
+pub const MAX_CHUNK_SIZE: usize = 3072;
+
+/// The maximum number of chunks an operation can be split into, bounded by
+/// the chunk hashes fitting in the header's inbox message
+pub const MAX_CHUNK_COUNT: u32 = 96;
+
+/// The maximum length (in bytes) of an encoded operation split into chunks
+pub const MAX_OPERATION_SIZE: u32 = MAX_CHUNK_COUNT * MAX_CHUNK_SIZE as u32;
+
+/// First byte of a versioned external message, followed by
+/// `EXTERNAL_MESSAGE_VERSION` and the bincode encoding of an
+/// `ExternalMessage`. Messages without it are decoded as a bare
+/// bincode-encoded `SignedOperation`, whose first byte is the tag of the
+/// signer's public key (0, 1 or 2), so that existing injectors keep working.
+pub const EXTERNAL_MESSAGE_TAG: u8 = 0xff;
+
+/// The version of the `ExternalMessage` encoding
+pub const EXTERNAL_MESSAGE_VERSION: u8 = 1;
+
+/// Returns the number of chunks an operation of `length` bytes is split into
+pub const fn chunk_count(length: u32) -> u32 {
+    (length as usize).div_ceil(MAX_CHUNK_SIZE) as u32
+}
+
+/// The payload of an external inbox message destined for jstz. See
+/// `EXTERNAL_MESSAGE_TAG` for its framing.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExternalMessage {
+    /// An operation that fits within a single inbox message
+    Operation(SignedOperation),
+    /// Announces an operation that is split across several chunk messages
+    ChunkHeader(ChunkHeader),
+    /// A slice of an announced operation
+    Chunk(Chunk),
+}
+
+/// Announces a chunked operation. The header carries the operation's
+/// signature, so that only its signer can announce it, and commits to the
+/// hash of each chunk, so that chunks are verified as they arrive.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChunkHeader {
+    /// Public key of the operation's signer
+    pub public_key: PublicKey,
+    /// Signature of the operation
+    pub signature: Signature,
+    /// Hash of the operation, as signed
+    pub operation_hash: OperationHash,
+    /// Length (in bytes) of the encoded operation
+    pub length: u32,
+    /// Hash of each chunk, in order
+    pub chunk_hashes: Vec<Blake2b>,
+}
+
+impl ChunkHeader {
+    /// Returns the number of chunks the operation is split into
+    pub fn chunk_count(&self) -> u32 {
+        chunk_count(self.length)
+    }
+
+    /// Hash of the header, identifying the chunk set
+    pub fn hash(&self) -> Blake2b {
+        let mut bytes = self.public_key.to_base58().into_bytes();
+        bytes.extend_from_slice(self.signature.to_base58().as_bytes());
+        bytes.extend_from_slice(self.operation_hash.as_ref());
+        bytes.extend_from_slice(&self.length.to_be_bytes());
+        for chunk_hash in &self.chunk_hashes {
+            bytes.extend_from_slice(chunk_hash.as_ref());
+        }
+        Blake2b::from(&bytes)
+    }
+
+    /// Verifies that the header was produced from an operation signed by
+    /// `public_key`
+    pub fn verify(&self) -> Result<()> {
+        self.signature
+            .verify(&self.public_key, self.operation_hash.as_ref())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Chunk {
+    /// Hash of the header of the chunk set this chunk belongs to
+    pub header_hash: Blake2b,
+    pub index: u32,
+    pub data: Vec<u8>,
+}
+
+impl ExternalMessage {
+    /// Splits a signed operation into a header followed by its chunks
+    pub fn split(
+        operation: &SignedOperation,
+        encoded_operation: &[u8],
+    ) -> Vec<ExternalMessage> {
+        let chunks: Vec<&[u8]> = encoded_operation.chunks(MAX_CHUNK_SIZE).collect();
+        let header = ChunkHeader {
+            public_key: operation.public_key.clone(),
+            signature: operation.signature.clone(),
+            operation_hash: operation.hash(),
+            length: encoded_operation.len() as u32,
+            chunk_hashes: chunks.iter().map(|data| Blake2b::from(*data)).collect(),
+        };
+        let header_hash = header.hash();
+
+        let chunks = chunks.into_iter().enumerate().map(|(index, data)| {
+            ExternalMessage::Chunk(Chunk {
+                header_hash: header_hash.clone(),
+                index: index as u32,
+                data: data.to_vec(),
+            })
+        });
+
+        std::iter::once(ExternalMessage::ChunkHeader(header))
+            .chain(chunks)
+            .collect()
+    }
+}
@@ -0,0 +1,500 @@
+//! Canonical binary encoding of operations.
+//!
+//! The encoding is hashed (with Blake2b-256) to obtain the operation hash,
+//! which is what clients sign. It is versioned and every variable-length
+//! field is length-prefixed, so distinct operations never share an encoding.
+//!
+//! Version 1 (all integers are big-endian):
+//!
+//! ```text
+//! operation := version:u8 (= 1) rollup_address:string expiry_level:option<u32>
+//!              source:string nonce:u64 content
+//! content   := 0x00 function_code:string account_credit:u64
+//!                   salt:option<bytes> metadata:option<metadata>
+//!                   gas_limit:option<u64> owner:option<string>  (DeployFunction)
+//!            | 0x01 uri:string method:string headers
+//!                   body:option<bytes> gas_limit:u64            (RunFunction)
+//!            | 0x02 receiver:string amount:u64                  (Withdraw)
+//!            | 0x03 count:u32 content*                          (Batch, not nested)
+//!            | 0x04 address:string function_code:string         (UpgradeFunction)
+//!            | 0x05 receiver:string amount:u64                  (Transfer)
+//!            | 0x06 root_hash:bytes account_credit:u64
+//!                   salt:option<bytes> metadata:option<metadata>
+//!                   gas_limit:option<u64> owner:option<string>  (RevealDeployFunction)
+//! headers   := count:u32 (name:string value:bytes)*  sorted by name, values of
+//!                                                    the same name kept in order
+//! metadata  := name:option<string> version:option<string>
+//!              description:option<string> source_hash:option<string>
+//!              bundler:option<string>
+//! string    := bytes (UTF-8)
+//! bytes     := length:u32 data
+//! option<x> := 0x00 | 0x01 x
+//! ```
+//!
+//! Addresses (including the rollup address) are encoded as base58 strings.
+
+use http::HeaderMap;
+
+use super::{
+    BatchItem, Content, DeployFunction, Operation, RevealDeployFunction, RunFunction,
+    Transfer, UpgradeFunction, Withdraw,
+};
+use crate::context::account::{Address, FunctionMetadata};
+
+pub const ENCODING_VERSION: u8 = 1;
+
+const DEPLOY_FUNCTION_TAG: u8 = 0;
+const RUN_FUNCTION_TAG: u8 = 1;
+const WITHDRAW_TAG: u8 = 2;
+const BATCH_TAG: u8 = 3;
+const UPGRADE_FUNCTION_TAG: u8 = 4;
+const TRANSFER_TAG: u8 = 5;
+const REVEAL_DEPLOY_FUNCTION_TAG: u8 = 6;
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value)
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_be_bytes())
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_be_bytes())
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value)
+    }
+
+    fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes())
+    }
+
+    fn option_u32(&mut self, value: &Option<u32>) {
+        match value {
+            None => self.u8(0),
+            Some(value) => {
+                self.u8(1);
+                self.u32(*value)
+            }
+        }
+    }
+
+    fn option_u64(&mut self, value: &Option<u64>) {
+        match value {
+            None => self.u8(0),
+            Some(value) => {
+                self.u8(1);
+                self.u64(*value)
+            }
+        }
+    }
+
+    fn option_bytes(&mut self, value: &Option<Vec<u8>>) {
+        match value {
+            None => self.u8(0),
+            Some(value) => {
+                self.u8(1);
+                self.bytes(value)
+            }
+        }
+    }
+
+    fn option_string(&mut self, value: &Option<String>) {
+        match value {
+            None => self.u8(0),
+            Some(value) => {
+                self.u8(1);
+                self.string(value)
+            }
+        }
+    }
+
+    fn metadata(&mut self, metadata: &FunctionMetadata) {
+        let FunctionMetadata {
+            name,
+            version,
+            description,
+            source_hash,
+            bundler,
+        } = metadata;
+
+        self.option_string(name);
+        self.option_string(version);
+        self.option_string(description);
+        self.option_string(source_hash);
+        self.option_string(bundler);
+    }
+
+    fn option_metadata(&mut self, value: &Option<FunctionMetadata>) {
+        match value {
+            None => self.u8(0),
+            Some(value) => {
+                self.u8(1);
+                self.metadata(value)
+            }
+        }
+    }
+
+    fn headers(&mut self, headers: &HeaderMap) {
+        let mut entries: Vec<_> = headers.iter().collect();
+        // Stable sort: values sharing a name keep their relative order
+        entries.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+        self.u32(entries.len() as u32);
+        for (name, value) in entries {
+            self.string(name.as_str());
+            self.bytes(value.as_bytes());
+        }
+    }
+
+    fn deploy_function(&mut self, deployment: &DeployFunction) {
+        let DeployFunction {
+            function_code,
+            account_credit,
+            salt,
+            metadata,
+            gas_limit,
+            owner,
+        } = deployment;
+
+        self.u8(DEPLOY_FUNCTION_TAG);
+        self.string(&function_code.to_string());
+        self.u64(*account_credit);
+        self.option_bytes(salt);
+        self.option_metadata(metadata);
+        self.option_u64(&gas_limit.map(|gas_limit| gas_limit as u64));
+        self.option_string(&owner.as_ref().map(Address::to_base58));
+    }
+
+    fn reveal_deploy_function(&mut self, deployment: &RevealDeployFunction) {
+        let RevealDeployFunction {
+            root_hash,
+            account_credit,
+            salt,
+            metadata,
+            gas_limit,
+            owner,
+        } = deployment;
+
+        self.u8(REVEAL_DEPLOY_FUNCTION_TAG);
+        self.bytes(root_hash);
+        self.u64(*account_credit);
+        self.option_bytes(salt);
+        self.option_metadata(metadata);
+        self.option_u64(&gas_limit.map(|gas_limit| gas_limit as u64));
+        self.option_string(&owner.as_ref().map(Address::to_base58));
+    }
+
+    fn run_function(&mut self, run: &RunFunction) {
+        let RunFunction {
+            uri,
+            method,
+            headers,
+            body,
+            gas_limit,
+        } = run;
+
+        self.u8(RUN_FUNCTION_TAG);
+        self.string(&uri.to_string());
+        self.string(method.as_str());
+        self.headers(headers);
+        self.option_bytes(body);
+        self.u64(*gas_limit as u64);
+    }
+
+    fn withdraw(&mut self, withdrawal: &Withdraw) {
+        let Withdraw { amount, receiver } = withdrawal;
+
+        self.u8(WITHDRAW_TAG);
+        self.string(&receiver.to_base58());
+        self.u64(*amount);
+    }
+
+    fn upgrade_function(&mut self, upgrade: &UpgradeFunction) {
+        let UpgradeFunction {
+            address,
+            function_code,
+        } = upgrade;
+
+        self.u8(UPGRADE_FUNCTION_TAG);
+        self.string(&address.to_base58());
+        self.string(&function_code.to_string());
+    }
+
+    fn transfer(&mut self, transfer: &Transfer) {
+        let Transfer { amount, receiver } = transfer;
+
+        self.u8(TRANSFER_TAG);
+        self.string(&receiver.to_base58());
+        self.u64(*amount);
+    }
+
+    fn batch_item(&mut self, item: &BatchItem) {
+        match item {
+            BatchItem::DeployFunction(deployment) => self.deploy_function(deployment),
+            BatchItem::RevealDeployFunction(deployment) => {
+                self.reveal_deploy_function(deployment)
+            }
+            BatchItem::RunFunction(run) => self.run_function(run),
+            BatchItem::Withdraw(withdrawal) => self.withdraw(withdrawal),
+            BatchItem::UpgradeFunction(upgrade) => self.upgrade_function(upgrade),
+            BatchItem::Transfer(transfer) => self.transfer(transfer),
+        }
+    }
+
+    fn content(&mut self, content: &Content) {
+        match content {
+            Content::DeployFunction(deployment) => self.deploy_function(deployment),
+            Content::RevealDeployFunction(deployment) => {
+                self.reveal_deploy_function(deployment)
+            }
+            Content::RunFunction(run) => self.run_function(run),
+            Content::Withdraw(withdrawal) => self.withdraw(withdrawal),
+            Content::UpgradeFunction(upgrade) => self.upgrade_function(upgrade),
+            Content::Transfer(transfer) => self.transfer(transfer),
+            Content::Batch(items) => {
+                self.u8(BATCH_TAG);
+                self.u32(items.len() as u32);
+                for item in items {
+                    self.batch_item(item);
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn encode_operation(operation: &Operation) -> Vec<u8> {
+    let Operation {
+        rollup_address,
+        expiry_level,
+        source,
+        nonce,
+        content,
+    } = operation;
+
+    let mut encoder = Encoder::default();
+    encoder.u8(ENCODING_VERSION);
+    encoder.string(&rollup_address.to_base58_check());
+    encoder.option_u32(expiry_level);
+    encoder.string(&source.to_base58());
+    encoder.u64(nonce.value());
+    encoder.content(content);
+    encoder.buf
+}
+
+#[cfg(test)]
+mod test {
+    use http::{HeaderMap, HeaderValue, Method, Uri};
+    use jstz_mock::mock;
+    use tezos_crypto_rs::hash::SmartRollupHash;
+
+    use crate::{
+        context::account::{FunctionMetadata, Nonce, ParsedCode},
+        operation::{Content, DeployFunction, Operation, RunFunction, Withdraw},
+    };
+
+    // Test vectors for clients implementing the encoding. The source of every
+    // operation is tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx, destined for the
+    // rollup sr1Ghq66tYK9y3r8CC1Tf8i8m5nxh8nTvZEf.
+
+    fn rollup_address() -> SmartRollupHash {
+        SmartRollupHash::from_base58_check("sr1Ghq66tYK9y3r8CC1Tf8i8m5nxh8nTvZEf")
+            .unwrap()
+    }
+
+    #[test]
+    fn withdraw_vector() {
+        let operation = Operation {
+            rollup_address: rollup_address(),
+            expiry_level: None,
+            source: mock::account1(),
+            nonce: Nonce::default(),
+            content: Content::Withdraw(Withdraw {
+                amount: 100,
+                receiver: mock::account1(),
+            }),
+        };
+
+        assert_eq!(
+            hex::encode(operation.encode()),
+            "0100000024737231476871363674594b397933723843433154663869386d356e7868386e54765a4566\
+             00\
+             00000024747a314b715470455a37596f62375162504534487934576f38664847384c684b785a5378\
+             0000000000000000\
+             0200000024747a314b715470455a37596f62375162504534487934576f38664847384c684b785a5378\
+             0000000000000064"
+        );
+        assert_eq!(
+            operation.hash().to_string(),
+            "704d0620b73668072232239ccc40b8435f46c91aeb12e03815875e702c9b454a"
+        );
+    }
+
+    #[test]
+    fn run_function_vector() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        let operation = Operation {
+            rollup_address: rollup_address(),
+            expiry_level: Some(42),
+            source: mock::account1(),
+            nonce: Nonce::default().next(),
+            content: Content::RunFunction(RunFunction {
+                uri: Uri::from_static("tezos://tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx/"),
+                method: Method::GET,
+                headers,
+                body: Some(b"hi".to_vec()),
+                gas_limit: 1000,
+            }),
+        };
+
+        assert_eq!(
+            operation.hash().to_string(),
+            "aaa0c18197346bd635d63e41d32533d797bfc0bce64a6925d0be565febe52b31"
+        );
+    }
+
+    #[test]
+    fn deploy_function_vector() {
+        let operation = Operation {
+            rollup_address: rollup_address(),
+            expiry_level: None,
+            source: mock::account1(),
+            nonce: Nonce::default(),
+            content: Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response();".to_string(),
+                )
+                .unwrap(),
+                account_credit: 0,
+                salt: None,
+                metadata: None,
+                gas_limit: None,
+                owner: None,
+            }),
+        };
+
+        assert_eq!(
+            operation.hash().to_string(),
+            "2b694f90a3daf9314a3f12291198fad5408ddea7df3e0749d5428f42dea3f810"
+        );
+    }
+
+    #[test]
+    fn salted_deploy_function_vector() {
+        let operation = Operation {
+            rollup_address: rollup_address(),
+            expiry_level: None,
+            source: mock::account1(),
+            nonce: Nonce::default(),
+            content: Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response();".to_string(),
+                )
+                .unwrap(),
+                account_credit: 0,
+                salt: Some(b"salt".to_vec()),
+                metadata: None,
+                gas_limit: None,
+                owner: None,
+            }),
+        };
+
+        assert_eq!(
+            operation.hash().to_string(),
+            "0de40d8ac75464df54b71ff22a005bd09212de76c0ddbb12ade6263e79131731"
+        );
+    }
+
+    #[test]
+    fn deploy_function_with_metadata_vector() {
+        let operation = Operation {
+            rollup_address: rollup_address(),
+            expiry_level: None,
+            source: mock::account1(),
+            nonce: Nonce::default(),
+            content: Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response();".to_string(),
+                )
+                .unwrap(),
+                account_credit: 0,
+                salt: None,
+                metadata: Some(FunctionMetadata {
+                    name: Some("hello".to_string()),
+                    version: Some("1.0.0".to_string()),
+                    bundler: Some("esbuild 0.19.5".to_string()),
+                    ..Default::default()
+                }),
+                gas_limit: None,
+                owner: None,
+            }),
+        };
+
+        assert_eq!(
+            operation.hash().to_string(),
+            "0e6987b83b83e221941bd3954aa7c9557d23be8e3a026e7dcb1090e8f2bc89c0"
+        );
+    }
+
+    #[test]
+    fn deploy_function_with_owner_vector() {
+        let operation = Operation {
+            rollup_address: rollup_address(),
+            expiry_level: None,
+            source: mock::account1(),
+            nonce: Nonce::default(),
+            content: Content::DeployFunction(DeployFunction {
+                function_code: ParsedCode::try_from(
+                    "export default () => new Response();".to_string(),
+                )
+                .unwrap(),
+                account_credit: 0,
+                salt: None,
+                metadata: None,
+                gas_limit: None,
+                owner: Some(mock::account1()),
+            }),
+        };
+
+        assert_eq!(
+            operation.hash().to_string(),
+            "e1174c58c351b94c8c60b56773a1e817898024a279f7ff28855ee4b2a6e0998d"
+        );
+    }
+
+    #[test]
+    fn headers_are_sorted_by_name() {
+        let operation = |headers: HeaderMap| Operation {
+            rollup_address: rollup_address(),
+            expiry_level: None,
+            source: mock::account1(),
+            nonce: Nonce::default(),
+            content: Content::RunFunction(RunFunction {
+                uri: Uri::from_static("tezos://tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx/"),
+                method: Method::GET,
+                headers,
+                body: None,
+                gas_limit: 0,
+            }),
+        };
+
+        let mut first = HeaderMap::new();
+        first.insert("a", HeaderValue::from_static("1"));
+        first.insert("b", HeaderValue::from_static("2"));
+        let mut second = HeaderMap::new();
+        second.insert("b", HeaderValue::from_static("2"));
+        second.insert("a", HeaderValue::from_static("1"));
+
+        assert_eq!(operation(first).hash(), operation(second).hash());
+    }
+}
@@ -0,0 +1,306 @@
+use http::{HeaderMap, Method, Uri};
+use jstz_api::http::body::HttpBody;
+use jstz_core::{host::HostRuntime, kv::Transaction};
+use jstz_crypto::{hash::Blake2b, public_key::PublicKey, signature::Signature};
+use serde::{Deserialize, Serialize};
+use tezos_crypto_rs::hash::SmartRollupHash;
+
+use crate::{
+    context::{
+        account::{Account, Address, Amount, FunctionMetadata, Nonce, ParsedCode},
+        block::Block,
+    },
+    Error, Result,
+};
+
+pub mod chunk;
+pub mod encoding;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Operation {
+    /// The rollup the operation is destined for
+    pub rollup_address: SmartRollupHash,
+    /// The last level at which the operation may be applied
+    pub expiry_level: Option<u32>,
+    pub source: Address,
+    pub nonce: Nonce,
+    pub content: Content,
+}
+
+pub type OperationHash = Blake2b;
+
+impl Operation {
+    /// Returns the source of the operation
+    pub fn source(&self) -> &Address {
+        &self.source
+    }
+
+    /// Returns the nonce of the operation
+    pub fn nonce(&self) -> &Nonce {
+        &self.nonce
+    }
+
+    /// Verify the nonce of the operation
+    /// Returns the operation's
+    pub fn verify_nonce(
+        &self,
+        rt: &impl HostRuntime,
+        tx: &mut Transaction,
+    ) -> Result<()> {
+        let next_nonce = Account::nonce(rt, tx, &self.source)?;
+
+        if self.nonce == *next_nonce {
+            next_nonce.increment();
+            Ok(())
+        } else {
+            Err(Error::InvalidNonce {
+                expected: *next_nonce,
+                actual: self.nonce,
+            })
+        }
+    }
+
+    /// Verify that the operation targets this rollup and hasn't expired
+    pub fn verify_scope(&self, rt: &impl HostRuntime) -> Result<()> {
+        if rt.reveal_metadata().address().hash() != &self.rollup_address {
+            return Err(Error::InvalidRollupAddress);
+        }
+
+        if let Some(expiry_level) = self.expiry_level {
+            if Block::level(rt)? > expiry_level {
+                return Err(Error::OperationExpired);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the canonical binary encoding of the operation.
+    /// See [`encoding`] for the format.
+    pub fn encode(&self) -> Vec<u8> {
+        encoding::encode_operation(self)
+    }
+
+    /// Computes the operation hash.
+    /// This is the hash which the client should sign
+    pub fn hash(&self) -> OperationHash {
+        Blake2b::from(&self.encode())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct DeployFunction {
+    pub function_code: ParsedCode,
+    pub account_credit: Amount,
+    /// When set, the address of the smart function is derived from the salt
+    /// and the code instead of the source's nonce (see [`salted_address`])
+    ///
+    /// [`salted_address`]: crate::context::account::salted_address
+    #[serde(default)]
+    pub salt: Option<Vec<u8>>,
+    /// Describes the smart function, stored alongside its account
+    #[serde(default)]
+    pub metadata: Option<FunctionMetadata>,
+    /// When set, the module of the smart function is evaluated and its `init`
+    /// export (if any) is run, with the given gas limit
+    #[serde(default)]
+    pub gas_limit: Option<usize>,
+    /// The address allowed to upgrade the smart function. Without an owner,
+    /// the smart function can never be upgraded.
+    #[serde(default)]
+    pub owner: Option<Address>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct RunFunction {
+    #[serde(with = "http_serde::uri")]
+    pub uri: Uri,
+    #[serde(with = "http_serde::method")]
+    pub method: Method,
+    #[serde(with = "http_serde::header_map")]
+    pub headers: HeaderMap,
+    pub body: HttpBody,
+    pub gas_limit: usize,
+}
+
+/// Replaces the code of a smart function owned by the source
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UpgradeFunction {
+    pub address: Address,
+    pub function_code: ParsedCode,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Withdraw {
+    pub amount: Amount,
+    /// The L1 address receiving the withdrawn tez
+    pub receiver: Address,
+}
+
+/// Deploys a smart function whose code is too large to fit in an inbox
+/// message. The code is revealed from the reveal data pages rooted at
+/// `root_hash` (see [`crate::preimage`]).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct RevealDeployFunction {
+    pub root_hash: Vec<u8>,
+    pub account_credit: Amount,
+    /// When set, the address of the smart function is derived from the salt
+    /// and the code instead of the source's nonce (see [`salted_address`])
+    ///
+    /// [`salted_address`]: crate::context::account::salted_address
+    #[serde(default)]
+    pub salt: Option<Vec<u8>>,
+    /// Describes the smart function, stored alongside its account
+    #[serde(default)]
+    pub metadata: Option<FunctionMetadata>,
+    /// When set, the module of the smart function is evaluated and its `init`
+    /// export (if any) is run, with the given gas limit
+    #[serde(default)]
+    pub gas_limit: Option<usize>,
+    /// The address allowed to upgrade the smart function. Without an owner,
+    /// the smart function can never be upgraded.
+    #[serde(default)]
+    pub owner: Option<Address>,
+}
+
+/// Transfers native tez from the source to another jstz account
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Transfer {
+    pub amount: Amount,
+    pub receiver: Address,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum Content {
+    DeployFunction(DeployFunction),
+    RevealDeployFunction(RevealDeployFunction),
+    RunFunction(RunFunction),
+    Withdraw(Withdraw),
+    UpgradeFunction(UpgradeFunction),
+    Transfer(Transfer),
+    /// Contents executed in order, atomically
+    Batch(Vec<BatchItem>),
+}
+
+/// The content of a batch. Mirrors [`Content`] without [`Content::Batch`], so
+/// that nested batches are rejected when decoding.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum BatchItem {
+    DeployFunction(DeployFunction),
+    RevealDeployFunction(RevealDeployFunction),
+    RunFunction(RunFunction),
+    Withdraw(Withdraw),
+    UpgradeFunction(UpgradeFunction),
+    Transfer(Transfer),
+}
+
+impl From<BatchItem> for Content {
+    fn from(item: BatchItem) -> Self {
+        match item {
+            BatchItem::DeployFunction(deployment) => Content::DeployFunction(deployment),
+            BatchItem::RevealDeployFunction(deployment) => {
+                Content::RevealDeployFunction(deployment)
+            }
+            BatchItem::RunFunction(run) => Content::RunFunction(run),
+            BatchItem::Withdraw(withdrawal) => Content::Withdraw(withdrawal),
+            BatchItem::UpgradeFunction(upgrade) => Content::UpgradeFunction(upgrade),
+            BatchItem::Transfer(transfer) => Content::Transfer(transfer),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedOperation {
+    pub public_key: PublicKey,
+    signature: Signature,
+    inner: Operation,
+}
+
+impl SignedOperation {
+    pub fn new(public_key: PublicKey, signature: Signature, inner: Operation) -> Self {
+        Self {
+            public_key,
+            signature,
+            inner,
+        }
+    }
+
+    pub fn hash(&self) -> Blake2b {
+        self.inner.hash()
+    }
+
+    pub fn verify(self) -> Result<Operation> {
+        // FIXME: Adding signature verification kills to the rollup???!??!?!?!
+        let hash = self.inner.hash();
+        self.signature.verify(&self.public_key, hash.as_ref())?;
+
+        Ok(self.inner)
+    }
+}
+
+pub mod external {
+    use super::*;
+
+    /// Identifies an inbox message by its L1 level and its index within that level
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct InboxId {
+        pub l1_level: u32,
+        pub l1_message_id: u32,
+    }
+
+    impl InboxId {
+        /// Computes a hash that uniquely identifies the inbox message.
+        /// Used to key receipts of inbox-originated operations.
+        pub fn hash(&self) -> OperationHash {
+            let mut bytes = self.l1_level.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&self.l1_message_id.to_be_bytes());
+            Blake2b::from(&bytes)
+        }
+    }
+
+    /// A deposit of native tez tickets, credited to the receiver's account
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct Deposit {
+        pub inbox_id: InboxId,
+        pub amount: Amount,
+        pub reciever: Address,
+    }
+
+    impl Deposit {
+        pub fn hash(&self) -> OperationHash {
+            self.inbox_id.hash()
+        }
+    }
+
+    /// A deposit of an arbitrary FA2.1 ticket, credited to the ticket table
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct FaDeposit {
+        pub inbox_id: InboxId,
+        pub amount: Amount,
+        pub receiver: Address,
+        /// Hash of the ticket's ticketer and contents
+        pub ticket_hash: Blake2b,
+    }
+
+    impl FaDeposit {
+        pub fn hash(&self) -> OperationHash {
+            self.inbox_id.hash()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExternalOperation {
+    Deposit(external::Deposit),
+    FaDeposit(external::FaDeposit),
+}
+
+impl ExternalOperation {
+    /// Hash of the inbox message the operation originates from
+    pub fn hash(&self) -> OperationHash {
+        match self {
+            ExternalOperation::Deposit(deposit) => deposit.hash(),
+            ExternalOperation::FaDeposit(fa_deposit) => fa_deposit.hash(),
+        }
+    }
+}
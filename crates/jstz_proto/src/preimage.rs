@@ -0,0 +1,75 @@
+//! Content that is too large to fit in an inbox message (such as the code of
+//! large smart functions) is split into reveal data pages (preimages), arranged
+//! as a Merkle tree. Only the root hash is sent to the rollup, and the content
+//! is reassembled in the kernel by revealing the pages.
+
+use jstz_core::host::HostRuntime;
+use tezos_smart_rollup_encoding::dac::{
+    pages::{prepare_preimages, reveal_loop, V0SliceContentPage, MAX_PAGE_SIZE},
+    PreimageHash,
+};
+
+use crate::{Error, Result};
+
+/// Size of a reveal data page hash
+pub const PREIMAGE_HASH_SIZE: usize = 33;
+
+/// Maximum depth of the tree of pages. 3 levels of hash pages below the root
+/// can address far more content than a smart function's code.
+const MAX_PAGE_LEVELS: usize = 4;
+
+/// Maximum size of revealed content
+pub const MAX_CONTENT_SIZE: usize = 1024 * 1024;
+
+/// Gas charged for each revealed page of content
+pub const REVEAL_GAS_PER_PAGE: usize = MAX_PAGE_SIZE;
+
+/// Splits `content` into pages, passing each page (and its hash) to `handle`.
+/// Returns the root hash of the tree of pages.
+pub fn prepare(
+    content: &[u8],
+    handle: impl FnMut(PreimageHash, Vec<u8>),
+) -> Result<PreimageHash> {
+    prepare_preimages(content, handle).map_err(|_| Error::InvalidPreimage)
+}
+
+/// Reassembles the content whose pages are rooted at `root_hash`, charging
+/// `REVEAL_GAS_PER_PAGE` for each page of content. Returns the content and the
+/// gas used. Fails with `GasLimitExceeded` once `gas_limit` is spent, and with
+/// `InvalidPreimage` once the content exceeds `MAX_CONTENT_SIZE`.
+pub fn reveal(
+    hrt: &mut impl HostRuntime,
+    root_hash: &[u8; PREIMAGE_HASH_SIZE],
+    gas_limit: usize,
+) -> Result<(Vec<u8>, usize)> {
+    let mut buffer = vec![0; MAX_PAGE_SIZE * MAX_PAGE_LEVELS];
+    let mut content = Vec::new();
+    let mut gas_used = 0;
+    let mut save_content =
+        |_: &mut _, page: V0SliceContentPage| -> std::result::Result<(), &'static str> {
+            gas_used += REVEAL_GAS_PER_PAGE;
+            if gas_used > gas_limit {
+                return Err("gas limit exceeded");
+            }
+            if content.len() + page.as_ref().len() > MAX_CONTENT_SIZE {
+                return Err("content too large");
+            }
+            content.extend_from_slice(page.as_ref());
+            Ok(())
+        };
+
+    let result = reveal_loop(
+        hrt,
+        0,
+        root_hash,
+        &mut buffer,
+        MAX_PAGE_LEVELS,
+        &mut save_content,
+    );
+    if gas_used > gas_limit {
+        return Err(Error::GasLimitExceeded);
+    }
+    result.map_err(|_| Error::InvalidPreimage)?;
+
+    Ok((content, gas_used))
+}
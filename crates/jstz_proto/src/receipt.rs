@@ -1,10 +1,167 @@
+use derive_more::Display;
 use http::{HeaderMap, StatusCode};
 use jstz_api::http::body::HttpBody;
+use jstz_core::error::KvError;
+use jstz_crypto::hash::Blake2b;
 use serde::{Deserialize, Serialize};
 
-use crate::{context::account::Address, operation::OperationHash, Result};
+use crate::{
+    context::{
+        account::{Address, Amount, Nonce},
+        outbox::{OutboxError, OutboxMessageId},
+        ticket_table::TicketTableError,
+    },
+    js_logger::LogRecord,
+    operation::OperationHash,
+    Error, Result,
+};
 
-pub type ReceiptResult<T> = std::result::Result<T, String>;
+pub type ReceiptResult<T> = std::result::Result<T, ReceiptError>;
+
+/// The error of a failed operation, as recorded in its receipt.
+/// Mirrors [`Error`] in a stable, serializable form.
+#[derive(Display, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceiptError {
+    #[display(fmt = "InvalidNonce (expected: {expected}, actual: {actual})")]
+    InvalidNonce {
+        expected: Nonce,
+        actual: Nonce,
+    },
+    #[display(fmt = "InsufficientFunds (balance: {balance}, amount: {amount})")]
+    InsufficientFunds {
+        balance: Amount,
+        amount: Amount,
+    },
+    BalanceOverflow,
+    GasLimitExceeded,
+    #[display(fmt = "{name}: {message}")]
+    JsException {
+        name: String,
+        message: String,
+    },
+    InvalidAddress,
+    InvalidSignature,
+    InvalidHash,
+    InvalidSecretKey,
+    InvalidCurve,
+    /// A base58 or binary encoded key, address or hash couldn't be decoded
+    InvalidEncoding,
+    /// A cryptographic primitive failed, e.g. on an invalid public key
+    CryptoFailure,
+    InvalidRollupAddress,
+    OperationExpired,
+    UnauthorizedUpgrade,
+    RefererShouldNotBeSet,
+    InvalidHttpRequest,
+    /// The reveal data pages of a `RevealDeployFunction` are missing,
+    /// malformed or too large
+    InvalidPreimage,
+    /// A concurrent write conflicted with the values read by a transaction
+    KvConflict,
+    #[display(fmt = "OutboxError: {error}")]
+    OutboxError {
+        error: OutboxError,
+    },
+    BatchRunFunctionFailed,
+    /// A smart function is already deployed at the address of a deployment
+    AlreadyDeployed,
+    /// The item of a batch at `index` failed, rolling back the whole batch
+    #[display(fmt = "BatchItemFailed (index: {index}): {error}")]
+    BatchItemFailed {
+        index: u32,
+        error: Box<ReceiptError>,
+    },
+    /// The `init` export of a deployed smart function responded with a
+    /// non-2xx status
+    InitFailed,
+    /// The smart function run by a `RunFunction` failed, after consuming
+    /// `gas_used` and emitting `logs`
+    #[display(fmt = "RunFunctionFailed (gas used: {gas_used}): {error}")]
+    RunFunctionFailed {
+        error: Box<ReceiptError>,
+        gas_used: usize,
+        logs: Vec<LogRecord>,
+    },
+    InsufficientTicketFunds,
+    /// Any other error, described by its message
+    #[display(fmt = "{message}")]
+    Other {
+        message: String,
+    },
+}
+
+impl From<Error> for ReceiptError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::InvalidNonce { expected, actual } => {
+                ReceiptError::InvalidNonce { expected, actual }
+            }
+            Error::InsufficientFunds { balance, amount } => {
+                ReceiptError::InsufficientFunds { balance, amount }
+            }
+            Error::BalanceOverflow => ReceiptError::BalanceOverflow,
+            Error::GasLimitExceeded => ReceiptError::GasLimitExceeded,
+            Error::CoreError {
+                source:
+                    jstz_core::Error::KvError {
+                        source: KvError::Conflict,
+                    },
+            } => ReceiptError::KvConflict,
+            Error::CoreError {
+                source: jstz_core::Error::JsError { source },
+            } => ReceiptError::JsException {
+                name: "Error".to_string(),
+                message: source.to_string(),
+            },
+            Error::JsException { name, message } => {
+                ReceiptError::JsException { name, message }
+            }
+            Error::InvalidAddress => ReceiptError::InvalidAddress,
+            Error::CryptoError { source } => match source {
+                jstz_crypto::Error::InvalidSignature => ReceiptError::InvalidSignature,
+                jstz_crypto::Error::InvalidHash => ReceiptError::InvalidHash,
+                jstz_crypto::Error::InvalidSecretKey => ReceiptError::InvalidSecretKey,
+                jstz_crypto::Error::InvalidCurve => ReceiptError::InvalidCurve,
+                jstz_crypto::Error::TezosFromBase58Error { .. }
+                | jstz_crypto::Error::TezosFromBytesError { .. } => {
+                    ReceiptError::InvalidEncoding
+                }
+                jstz_crypto::Error::TezosCryptoError { .. } => {
+                    ReceiptError::CryptoFailure
+                }
+            },
+            Error::InvalidRollupAddress => ReceiptError::InvalidRollupAddress,
+            Error::OperationExpired => ReceiptError::OperationExpired,
+            Error::UnauthorizedUpgrade => ReceiptError::UnauthorizedUpgrade,
+            Error::RefererShouldNotBeSet => ReceiptError::RefererShouldNotBeSet,
+            Error::InvalidHttpRequest => ReceiptError::InvalidHttpRequest,
+            Error::InvalidPreimage => ReceiptError::InvalidPreimage,
+            Error::OutboxError { source } => ReceiptError::OutboxError { error: source },
+            Error::BatchRunFunctionFailed => ReceiptError::BatchRunFunctionFailed,
+            Error::AlreadyDeployed => ReceiptError::AlreadyDeployed,
+            Error::BatchItemFailed { index, source } => ReceiptError::BatchItemFailed {
+                index: index as u32,
+                error: Box::new(ReceiptError::from(*source)),
+            },
+            Error::InitFailed => ReceiptError::InitFailed,
+            Error::RunFunctionFailed {
+                source,
+                gas_used,
+                logs,
+            } => ReceiptError::RunFunctionFailed {
+                error: Box::new(ReceiptError::from(*source)),
+                gas_used,
+                logs,
+            },
+            Error::TicketTableError {
+                source: TicketTableError::InsufficientFunds,
+            } => ReceiptError::InsufficientTicketFunds,
+            error => ReceiptError::Other {
+                message: error.to_string(),
+            },
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Receipt {
@@ -14,7 +171,7 @@ pub struct Receipt {
 
 impl Receipt {
     pub fn new(hash: OperationHash, inner: Result<Content>) -> Self {
-        let inner = inner.map_err(|e| e.to_string());
+        let inner = inner.map_err(ReceiptError::from);
         Self { hash, inner }
     }
 
@@ -35,10 +192,151 @@ pub struct RunFunction {
     pub status_code: StatusCode,
     #[serde(with = "http_serde::header_map")]
     pub headers: HeaderMap,
+    /// Number of instructions consumed by the smart function
+    pub gas_used: usize,
+    /// Number of key-value pairs written (or removed) by the smart function
+    pub kv_writes: usize,
+    /// Total size (in bytes) of the values written by the smart function
+    pub kv_write_bytes: usize,
+    /// Console output of the smart function
+    pub logs: Vec<LogRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Withdraw {
+    /// The outbox message to execute on L1 to receive the withdrawn tez
+    pub outbox_message_id: OutboxMessageId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeFunction {
+    pub address: Address,
+    #[serde(with = "jstz_crypto::hash::hex_string")]
+    pub previous_code_hash: Blake2b,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    pub receiver: Address,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deposit {
+    pub receiver: Address,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaDeposit {
+    pub receiver: Address,
+    #[serde(with = "jstz_crypto::hash::hex_string")]
+    pub ticket_hash: Blake2b,
+    pub amount: Amount,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Content {
     DeployFunction(DeployFunction),
     RunFunction(RunFunction),
+    Withdraw(Withdraw),
+    UpgradeFunction(UpgradeFunction),
+    Transfer(Transfer),
+    Deposit(Deposit),
+    FaDeposit(FaDeposit),
+    /// The receipts of each content of a batch, in order
+    Batch(Vec<Content>),
+}
+
+#[cfg(test)]
+mod test {
+    use jstz_mock::mock;
+
+    use crate::{
+        context::{account::Nonce, outbox::OutboxError},
+        Error,
+    };
+
+    use super::{Content, FaDeposit, ReceiptError};
+
+    #[test]
+    fn receipt_error_mirrors_error() {
+        let error = Error::InvalidNonce {
+            expected: Nonce::default().next(),
+            actual: Nonce::default(),
+        };
+        let receipt_error = ReceiptError::from(error);
+
+        assert_eq!(
+            receipt_error,
+            ReceiptError::InvalidNonce {
+                expected: Nonce::default().next(),
+                actual: Nonce::default(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&receipt_error).unwrap(),
+            r#"{"InvalidNonce":{"expected":1,"actual":0}}"#
+        );
+        assert_eq!(
+            receipt_error.to_string(),
+            "InvalidNonce (expected: 1, actual: 0)"
+        );
+    }
+
+    #[test]
+    fn receipt_error_types_host_errors() {
+        assert_eq!(
+            ReceiptError::from(Error::OutboxError {
+                source: OutboxError::OutboxFull
+            }),
+            ReceiptError::OutboxError {
+                error: OutboxError::OutboxFull
+            }
+        );
+        assert_eq!(
+            ReceiptError::from(Error::InvalidPreimage),
+            ReceiptError::InvalidPreimage
+        );
+        assert_eq!(
+            ReceiptError::from(Error::CoreError {
+                source: jstz_core::Error::KvError {
+                    source: jstz_core::error::KvError::Conflict
+                }
+            }),
+            ReceiptError::KvConflict
+        );
+        assert_eq!(
+            ReceiptError::from(Error::CryptoError {
+                source: jstz_crypto::Error::InvalidHash
+            }),
+            ReceiptError::InvalidHash
+        );
+    }
+
+    #[test]
+    fn receipt_ticket_hash_is_hex() {
+        let ticket_hash = mock::ticket_hash1();
+        let content = Content::FaDeposit(FaDeposit {
+            receiver: mock::account1(),
+            ticket_hash: ticket_hash.clone(),
+            amount: 10,
+        });
+
+        let json = serde_json::to_value(&content).unwrap();
+        assert_eq!(
+            json["FaDeposit"]["ticket_hash"],
+            serde_json::Value::String(ticket_hash.to_string())
+        );
+
+        let content: Content = serde_json::from_value(json).unwrap();
+        let Content::FaDeposit(FaDeposit {
+            ticket_hash: decoded,
+            ..
+        }) = content
+        else {
+            panic!("expected a FaDeposit receipt")
+        };
+        assert_eq!(decoded, ticket_hash);
+    }
 }
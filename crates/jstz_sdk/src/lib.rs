@@ -1,5 +1,5 @@
-use jstz_crypto::secret_key::SecretKey;
-use jstz_proto::operation::Operation;
+use jstz_crypto::{hash::Blake2b, public_key_hash::PublicKeyHash, secret_key::SecretKey};
+use jstz_proto::{context::account, operation::Operation};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -21,3 +21,27 @@ pub fn hash_operation(operation: JsValue) -> Result<String, JsValue> {
     let operation: Operation = serde_wasm_bindgen::from_value(operation)?;
     Ok(operation.hash().to_string())
 }
+
+#[wasm_bindgen]
+pub fn encode_operation(operation: JsValue) -> Result<String, JsValue> {
+    let operation: Operation = serde_wasm_bindgen::from_value(operation)?;
+    Ok(hex::encode(operation.encode()))
+}
+
+/// Computes the address of the smart function deployed by `deployer` with
+/// the given salt and code
+#[wasm_bindgen]
+pub fn salted_address(
+    deployer: &str,
+    salt: &[u8],
+    function_code: &str,
+) -> Result<String, JsValue> {
+    let deployer = PublicKeyHash::from_base58(deployer)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let code_hash = Blake2b::from(function_code.as_bytes());
+
+    let address = account::salted_address(&deployer, salt, &code_hash)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(address.to_base58())
+}
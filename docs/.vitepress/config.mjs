@@ -29,6 +29,7 @@ export default defineConfig({
           { text: "Quick Start", link: "/quick_start" },
           { text: "CLI", link: "/cli" },
           { text: "Asset Bridge", link: "/bridge" },
+          { text: "Operation Encoding", link: "/operation_encoding" },
         ],
       },
 
@@ -40,6 +41,8 @@ export default defineConfig({
           { text: "KV", link: "/api/kv" },
           { text: "SmartFunction", link: "/api/smart_function" },
           { text: "Ledger", link: "/api/ledger" },
+          { text: "Tickets", link: "/api/tickets" },
+          { text: "Block", link: "/api/block" },
           { text: "Headers", link: "/api/headers" },
           { text: "Request", link: "/api/request" },
           { text: "Response", link: "/api/response" },
@@ -1,13 +1,21 @@
 import * as jstz from "jstz_sdk";
 
 namespace ffi {
-  export type Address = { Tz1: string };
+  export type Address = { Tz1: string } | { Tz2: string } | { Tz3: string };
 
-  export type Signature = { Ed25519: string };
+  export type Signature =
+    | { Ed25519: string }
+    | { Secp256k1: string }
+    | { P256: string };
 
-  export type PublicKey = { Ed25519: string };
+  export type PublicKey =
+    | { Ed25519: string }
+    | { Secp256k1: string }
+    | { P256: string };
 
   export type Operation = {
+    rollup_address: string;
+    expiry_level: number | null;
     source: Address;
     nonce: number;
     content: OperationContent;
@@ -16,8 +24,35 @@ namespace ffi {
   export type Headers = Record<string, string>;
   export type Body = Uint8Array;
 
-  export type OperationContent =
-    | { DeployFunction: { function_code: string; account_credit: number } }
+  export type FunctionMetadata = {
+    name: string | null;
+    version: string | null;
+    description: string | null;
+    source_hash: string | null;
+    bundler: string | null;
+  };
+
+  export type BatchItem =
+    | {
+        DeployFunction: {
+          function_code: string;
+          account_credit: number;
+          salt: Uint8Array | null;
+          metadata: FunctionMetadata | null;
+          gas_limit: number | null;
+          owner: Address | null;
+        };
+      }
+    | {
+        RevealDeployFunction: {
+          root_hash: Uint8Array;
+          account_credit: number;
+          salt: Uint8Array | null;
+          metadata: FunctionMetadata | null;
+          gas_limit: number | null;
+          owner: Address | null;
+        };
+      }
     | {
         RunFunction: {
           uri: string;
@@ -26,7 +61,12 @@ namespace ffi {
           body: Body | null;
           gas_limit: number;
         };
-      };
+      }
+    | { Withdraw: { amount: number; receiver: Address } }
+    | { UpgradeFunction: { address: Address; function_code: string } }
+    | { Transfer: { amount: number; receiver: Address } };
+
+  export type OperationContent = BatchItem | { Batch: BatchItem[] };
 
   export type SignedOperation = {
     public_key: PublicKey;
@@ -34,12 +74,61 @@ namespace ffi {
     inner: Operation;
   };
 
+  export type LogRecord = {
+    address: Address;
+    request_id: string;
+    level: "ERROR" | "WARN" | "INFO" | "LOG";
+    text: string;
+  };
+
   export type Receipt = {
     hash: Uint8Array;
     inner: ReceiptResult;
   };
 
-  export type ReceiptResult = { Ok: ReceiptContent } | { Err: string };
+  export type ReceiptResult = { Ok: ReceiptContent } | { Err: ReceiptError };
+
+  export type OutboxError =
+    | "OutboxFull"
+    | "TicketerNotFound"
+    | "InvalidTicket"
+    | "InvalidReceiver"
+    | "EncodingFailed";
+
+  export type ReceiptError =
+    | "BalanceOverflow"
+    | "GasLimitExceeded"
+    | "InvalidAddress"
+    | "InvalidSignature"
+    | "InvalidHash"
+    | "InvalidSecretKey"
+    | "InvalidCurve"
+    | "InvalidEncoding"
+    | "CryptoFailure"
+    | "InvalidRollupAddress"
+    | "OperationExpired"
+    | "UnauthorizedUpgrade"
+    | "RefererShouldNotBeSet"
+    | "InvalidHttpRequest"
+    | "InvalidPreimage"
+    | "KvConflict"
+    | "BatchRunFunctionFailed"
+    | "AlreadyDeployed"
+    | "InitFailed"
+    | "InsufficientTicketFunds"
+    | { InvalidNonce: { expected: number; actual: number } }
+    | { InsufficientFunds: { balance: number; amount: number } }
+    | { JsException: { name: string; message: string } }
+    | { OutboxError: { error: OutboxError } }
+    | { BatchItemFailed: { index: number; error: ReceiptError } }
+    | {
+        RunFunctionFailed: {
+          error: ReceiptError;
+          gas_used: number;
+          logs: LogRecord[];
+        };
+      }
+    | { Other: { message: string } };
 
   export type ReceiptContent =
     | {
@@ -47,29 +136,91 @@ namespace ffi {
           body: Body;
           status_code: number;
           headers: Headers;
+          gas_used: number;
+          kv_writes: number;
+          kv_write_bytes: number;
+          logs: LogRecord[];
         };
       }
     | {
         DeployFunction: {
           address: Address;
         };
-      };
+      }
+    | {
+        Withdraw: {
+          outbox_message_id: { level: number; index: number };
+        };
+      }
+    | {
+        Transfer: {
+          receiver: Address;
+          amount: number;
+        };
+      }
+    | {
+        Deposit: {
+          receiver: Address;
+          amount: number;
+        };
+      }
+    | {
+        FaDeposit: {
+          receiver: Address;
+          ticket_hash: string;
+          amount: number;
+        };
+      }
+    | {
+        UpgradeFunction: {
+          address: Address;
+          previous_code_hash: string;
+        };
+      }
+    | { Batch: ReceiptContent[] };
 }
 
 export type Address = string;
 
-const ADDRESS_REGEX = /^tz1[a-zA-Z0-9]{33}$/;
+const ADDRESS_REGEX = /^tz[123][a-zA-Z0-9]{33}$/;
 
 export function isAddress(value: unknown): value is Address {
   return typeof value === "string" && value.match(ADDRESS_REGEX) !== null;
 }
 
+/**
+ * Computes the address of the smart function deployed by `deployer` with the
+ * given salt and code, without deploying it.
+ */
+export function saltedAddress(
+  deployer: Address,
+  salt: Uint8Array,
+  functionCode: string,
+): Address {
+  return jstz.salted_address(deployer, salt, functionCode);
+}
+
 interface Operation {
+  rollupAddress: string;
+  expiryLevel?: number;
   source: Address;
   nonce: number;
   content: OperationContent;
 }
 
+/**
+ * Metadata recorded alongside a smart function when it is deployed.
+ * `sourceHash` is the hex-encoded Blake2b-256 hash of the original
+ * (unbundled) source.
+ */
+export type FunctionMetadata = {
+  name?: string;
+  version?: string;
+  description?: string;
+  sourceHash?: string;
+  bundler?: string;
+};
+
 export type JstzHeaders = Record<string, string>;
 export type JstzBody = Uint8Array;
 export type JstzRequest = {
@@ -83,6 +234,10 @@ export type JstzResponse = {
   statusCode: number;
   headers: JstzHeaders;
   body: JstzBody;
+  gasUsed: number;
+  kvWrites: number;
+  kvWriteBytes: number;
+  logs: ffi.LogRecord[];
 };
 
 type OperationContent =
@@ -90,10 +245,19 @@ type OperationContent =
       kind: "deploy";
       functionCode: string;
       initialBalance: number;
+      salt?: Uint8Array;
+      metadata?: FunctionMetadata;
+      gasLimit?: number;
+      owner?: Address;
     }
   | ({
       kind: "run";
-    } & JstzRequest);
+    } & JstzRequest)
+  | {
+      kind: "transfer";
+      receiver: Address;
+      amount: number;
+    };
 
 export type User = {
   address: Address;
@@ -109,17 +273,49 @@ type SignedOperation = {
 };
 
 const encodeAddress = (address: Address): ffi.Address => {
+  if (address.startsWith("tz2")) return { Tz2: address };
+  if (address.startsWith("tz3")) return { Tz3: address };
   return { Tz1: address };
 };
 
 const encodeSignature = (signature: string): ffi.Signature => {
+  if (signature.startsWith("spsig")) return { Secp256k1: signature };
+  if (signature.startsWith("p2sig")) return { P256: signature };
   return { Ed25519: signature };
 };
 
 const encodePublicKey = (publicKey: string): ffi.PublicKey => {
+  if (publicKey.startsWith("sppk")) return { Secp256k1: publicKey };
+  if (publicKey.startsWith("p2pk")) return { P256: publicKey };
   return { Ed25519: publicKey };
 };
 
+const decodeAddress = (address: ffi.Address): Address => {
+  if ("Tz2" in address) return address.Tz2;
+  if ("Tz3" in address) return address.Tz3;
+  return address.Tz1;
+};
+
+const encodeMetadata = (
+  metadata: FunctionMetadata,
+): ffi.FunctionMetadata => ({
+  name: metadata.name ?? null,
+  version: metadata.version ?? null,
+  description: metadata.description ?? null,
+  source_hash: metadata.sourceHash ?? null,
+  bundler: metadata.bundler ?? null,
+});
+
+const decodeMetadata = (
+  metadata: ffi.FunctionMetadata,
+): FunctionMetadata => ({
+  name: metadata.name ?? undefined,
+  version: metadata.version ?? undefined,
+  description: metadata.description ?? undefined,
+  sourceHash: metadata.source_hash ?? undefined,
+  bundler: metadata.bundler ?? undefined,
+});
+
 const encodeOperationContent = (
   content: OperationContent,
 ): ffi.OperationContent => {
@@ -129,6 +325,14 @@ const encodeOperationContent = (
         DeployFunction: {
           function_code: content.functionCode,
           account_credit: content.initialBalance,
+          salt: content.salt === undefined ? null : content.salt,
+          metadata:
+            content.metadata === undefined
+              ? null
+              : encodeMetadata(content.metadata),
+          gas_limit: content.gasLimit === undefined ? null : content.gasLimit,
+          owner:
+            content.owner === undefined ? null : encodeAddress(content.owner),
         },
       };
     case "run":
@@ -141,13 +345,25 @@ const encodeOperationContent = (
           gas_limit: content.gasLimit || 1000,
         },
       };
+    case "transfer":
+      return {
+        Transfer: {
+          amount: content.amount,
+          receiver: encodeAddress(content.receiver),
+        },
+      };
   }
 };
 
+const formatReceiptError = (error: ffi.ReceiptError): string =>
+  typeof error === "string" ? error : JSON.stringify(error);
+
 const encodeOperation = (operation: Operation): ffi.Operation => {
-  const { source, nonce, content } = operation;
+  const { rollupAddress, expiryLevel, source, nonce, content } = operation;
 
   return {
+    rollup_address: rollupAddress,
+    expiry_level: expiryLevel === undefined ? null : expiryLevel,
     source: encodeAddress(source),
     nonce,
     content: encodeOperationContent(content),
@@ -196,6 +412,47 @@ export class Jstz {
     return (await res.json()) as number;
   }
 
+  async getMetadata(address: Address): Promise<FunctionMetadata | null> {
+    const res = await fetch(
+      `http://${this.endpoint}/accounts/${address}/metadata`,
+    );
+
+    if (res.status !== 200) {
+      throw new Error("Failed to fetch metadata");
+    }
+
+    const metadata = (await res.json()) as ffi.FunctionMetadata | null;
+    return metadata === null ? null : decodeMetadata(metadata);
+  }
+
+  /**
+   * Returns the hex-encoded hash of the code run by a smart function. Smart
+   * functions deployed from the same code share the same hash.
+   */
+  async getCodeHash(address: Address): Promise<string | null> {
+    const res = await fetch(
+      `http://${this.endpoint}/accounts/${address}/code_hash`,
+    );
+
+    if (res.status !== 200) {
+      throw new Error("Failed to fetch code hash");
+    }
+
+    return (await res.json()) as string | null;
+  }
+
+  async getRollupAddress(): Promise<string> {
+    const res = await fetch(
+      `http://${this.endpoint}/operations/rollup_address`,
+    );
+
+    if (res.status !== 200) {
+      throw new Error("Failed to fetch rollup address");
+    }
+
+    return (await res.json()) as string;
+  }
+
   private pollReceipt(hash: string): Promise<ffi.Receipt> {
     const endpoint = this.endpoint;
     return new Promise((resolve, reject) => {
@@ -237,16 +494,26 @@ export class Jstz {
     user: User,
     functionCode: string,
     initialBalance: number = 0,
+    salt?: Uint8Array,
+    metadata?: FunctionMetadata,
+    gasLimit?: number,
+    owner?: Address,
   ): Promise<Address> {
     const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
 
     const operation: Operation = {
+      rollupAddress,
       source: user.address,
       nonce,
       content: {
         kind: "deploy",
         functionCode,
         initialBalance,
+        salt,
+        metadata,
+        gasLimit,
+        owner,
       },
     };
 
@@ -255,7 +522,7 @@ export class Jstz {
     );
 
     if ("Err" in receipt.inner) {
-      throw new Error(receipt.inner.Err);
+      throw new Error(formatReceiptError(receipt.inner.Err));
     }
 
     const receiptContent = receipt.inner["Ok"];
@@ -264,13 +531,15 @@ export class Jstz {
       throw new Error("Unexpected receipt kind");
     }
 
-    return receiptContent.DeployFunction.address.Tz1;
+    return decodeAddress(receiptContent.DeployFunction.address);
   }
 
   async run(user: User, request: JstzRequest): Promise<JstzResponse> {
     const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
 
     const operation: Operation = {
+      rollupAddress,
       source: user.address,
       nonce,
       content: {
@@ -284,7 +553,7 @@ export class Jstz {
     );
 
     if ("Err" in receipt.inner) {
-      throw new Error(receipt.inner.Err);
+      throw new Error(formatReceiptError(receipt.inner.Err));
     }
 
     const receiptContent = receipt.inner["Ok"];
@@ -297,6 +566,38 @@ export class Jstz {
       statusCode: receiptContent.RunFunction.status_code,
       headers: receiptContent.RunFunction.headers,
       body: receiptContent.RunFunction.body,
+      gasUsed: receiptContent.RunFunction.gas_used,
+      kvWrites: receiptContent.RunFunction.kv_writes,
+      kvWriteBytes: receiptContent.RunFunction.kv_write_bytes,
+      logs: receiptContent.RunFunction.logs,
+    };
+  }
+
+  async transfer(user: User, receiver: Address, amount: number): Promise<void> {
+    const nonce = await this.getNonce(user.address);
+    const rollupAddress = await this.getRollupAddress();
+
+    const operation: Operation = {
+      rollupAddress,
+      source: user.address,
+      nonce,
+      content: {
+        kind: "transfer",
+        receiver,
+        amount,
+      },
     };
+
+    const receipt = await this.postSignedOperation(
+      signOperation(user, operation),
+    );
+
+    if ("Err" in receipt.inner) {
+      throw new Error(formatReceiptError(receipt.inner.Err));
+    }
+
+    if (!("Transfer" in receipt.inner["Ok"])) {
+      throw new Error("Unexpected receipt kind");
+    }
   }
 }
@@ -186,11 +186,24 @@ declare var console: Console;
 
 declare type Address = string;
 
+declare interface KvListOptions {
+  limit?: number;
+  cursor?: string;
+}
+
 declare interface Kv {
   get<T = unknown>(key: string): T | null;
   set(key: string, value: unknown): void;
   delete(key: string): void;
   has(key: string): boolean;
+  keys(prefix?: string, options?: KvListOptions): string[];
+  entries<T = unknown>(
+    prefix?: string,
+    options?: KvListOptions,
+  ): [string, T][];
+  hasUnlistedKeys(): boolean;
+  markListed(): void;
+  transaction<T>(callback: () => T): T;
 }
 
 declare var Kv: Kv;
@@ -201,17 +214,53 @@ declare interface Ledger {
   readonly selfAddress: Address;
   balance(address: Address): Mutez;
   transfer(address: Address, amount: Mutez): void;
+  withdraw(l1Address: Address, amount: Mutez): OutboxMessageId;
+}
+
+declare interface OutboxMessageId {
+  readonly level: number;
+  readonly index: number;
 }
 
 declare var Ledger: Ledger;
 
+declare type TicketHash = string;
+
+declare interface Tickets {
+  balance(owner: Address, ticketHash: TicketHash): number;
+  transfer(dst: Address, ticketHash: TicketHash, amount: number): void;
+}
+
+declare var Tickets: Tickets;
+
+declare interface SmartFunctionCreateOptions {
+  salt?: string | BufferSource;
+  owner?: Address | null;
+}
+
 declare interface SmartFunction {
-  create(code: String): Promise<Address>;
+  create(
+    code: String,
+    balance?: bigint,
+    options?: SmartFunctionCreateOptions,
+  ): Promise<Address>;
   call(request: Request): Promise<Response>;
 }
 
 declare var SmartFunction: SmartFunction;
 
+declare interface Block {
+  readonly level: number;
+  readonly timestamp: number;
+  readonly predecessor: string | null;
+}
+
+declare interface Jstz {
+  readonly block: Block;
+}
+
+declare var Jstz: Jstz;
+
 declare function fetch(request: Request): Promise<Response>;
 
 declare function atob(s: string): string;